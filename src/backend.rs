@@ -1,21 +1,21 @@
+use std::{
+    ops::Range,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use crate::{
-    epub::{Content, Epub},
+    book::Book,
+    config::Config,
+    epub::{Align, Content, TextOptions},
+    notes::Notes,
+    quotes::Quotes,
+    score::Score,
     style::{Style, Styling},
+    translit,
 };
 
-#[rustfmt::skip]
-const REPLACEMENTS: (&[char], &[&str]) = (
-    &['—', '…'],
-    &["--", "..."],
-);
-
-#[rustfmt::skip]
-const ALTERNATIVES: &[(char, &[char])] = &[
-    ('\'', &['‘', '’']),
-    ('\"', &['“', '”']),
-    (' ',  &[' '])
-];
-
 pub struct Backend {
     text: String,
     typed: String,
@@ -24,27 +24,285 @@ pub struct Backend {
     errors: Vec<Len>,
     deleted_errors: Vec<Len>,
     styling: Styling<Len>,
+    paragraphs: Vec<Range<Len>>,
+    /// Kind of block each paragraph is.
+    kinds: Vec<BlockKind>,
+    /// Alignment of each paragraph, where the book gives one.
+    alignments: Vec<Option<Align>>,
+    /// Text that's shown but not typed, such as list markers, which the
+    /// cursor moves over on its own.
+    skipped: Vec<Range<Len>>,
+    images: Vec<Image>,
+    notes: Vec<Note>,
+    created: Instant,
+    /// Time of the first keystroke since `created`.
+    started: Option<Duration>,
+    keystrokes: Vec<Keystroke>,
+    /// Keystrokes that weren't correct, counted as they're made.
+    mistyped: usize,
+    /// Every edit with its time since `created`, if recording.
+    log: Option<Vec<(Duration, Op)>>,
+    config: Arc<Config>,
+    /// Accept ASCII transliterations in place of characters.
+    transliterate: bool,
+    error_mode: ErrorMode,
+    /// Keys typed so far towards the transliteration of the character at the
+    /// cursor, when it takes more than one.
+    pending: String,
+}
+
+/// What a paragraph of the text was in the book.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum BlockKind {
+    #[default]
+    Paragraph,
+    Header,
+    Quote,
+    ListItem,
+    Preformatted,
+    TableRow,
+}
+
+/// An edit to the typed text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Op {
+    Push(char),
+    Pop,
+    DeleteWord,
+}
+
+/// What happens when a character is mistyped.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// The error is recorded and the cursor moves on.
+    #[default]
+    Advance,
+    /// The cursor doesn't move until the right character is typed.
+    Stop,
+    /// The cursor moves on, but a paragraph can't be left while it still has
+    /// errors.
+    Correct,
+}
+
+impl ErrorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Advance => "advance",
+            Self::Stop => "stop",
+            Self::Correct => "correct",
+        }
+    }
+
+    pub fn description(&self) -> Option<&'static str> {
+        match self {
+            Self::Advance => None,
+            Self::Stop => Some("stop on error"),
+            Self::Correct => Some("must correct errors"),
+        }
+    }
+}
+
+impl FromStr for ErrorMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "advance" => Ok(Self::Advance),
+            "stop" => Ok(Self::Stop),
+            "correct" => Ok(Self::Correct),
+            _ => anyhow::bail!("unknown error mode `{s}` (expected advance, stop or correct)"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Image {
+    /// Position in `text` the image comes after, always the end of a
+    /// paragraph or the start of the text.
+    pub position: Len,
+    pub alt: Option<String>,
+    /// `None` if the image couldn't be loaded from the book.
+    pub data: Option<Vec<u8>>,
+}
+
+/// A note shown beside the text rather than typed.
+#[derive(Debug)]
+pub struct Note {
+    /// Position in `text` of the end of the paragraph referring to the note.
+    pub position: Len,
+    pub text: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Keystroke {
+    /// Time since the first keystroke of the session.
+    pub time: Duration,
+    pub position: Len,
+    pub expected: char,
+    pub typed: char,
+    /// Whether `typed` was accepted for `expected`, which depends on the
+    /// alternatives configured at the time.
+    pub correct: bool,
 }
 
 impl Backend {
-    pub fn new(book: &mut Epub, chapter: usize) -> Self {
+    pub fn new(
+        book: &mut dyn Book,
+        chapter: usize,
+        quotes: Quotes,
+        notes: Notes,
+        config: Arc<Config>,
+        transliterate: bool,
+        error_mode: ErrorMode,
+    ) -> anyhow::Result<Self> {
         let mut text = String::new();
         let mut char_count = 0;
         let mut styling = Styling::builder().build();
-        book.traverse(chapter, &REPLACEMENTS, |content, _align| match content {
-            Content::Header(s, stys) | Content::Paragraph(s, stys) | Content::Quote(s, stys) => {
-                if !text.is_empty() {
-                    text.push('\n');
-                    char_count += 1;
+        let mut paragraphs = Vec::new();
+        let mut skipped = Vec::new();
+        let mut images = Vec::new();
+        let mut shown_notes = Vec::new();
+        let options = TextOptions {
+            replacements: config.replacements(),
+            quotes,
+            notes,
+        };
+        book.traverse(chapter, &options, &mut |content, align| {
+            let kind = match content {
+                Content::Header(..) => BlockKind::Header,
+                Content::Paragraph(..) => BlockKind::Paragraph,
+                Content::Quote(..) => BlockKind::Quote,
+                Content::ListItem(..) => BlockKind::ListItem,
+                Content::Preformatted(..) => BlockKind::Preformatted,
+                Content::TableRow(..) => BlockKind::TableRow,
+                Content::Image(href, alt) => {
+                    let alt = alt.map(ToOwned::to_owned);
+                    images.push((Len::new(text.len(), char_count), href.to_owned(), alt));
+                    return;
+                }
+                Content::Note(note) => {
+                    shown_notes.push(Note {
+                        position: Len::new(text.len(), char_count),
+                        text: note.to_owned(),
+                    });
+                    return;
+                }
+            };
+            if !text.is_empty() {
+                text.push('\n');
+                char_count += 1;
+            }
+            let start = Len::new(text.len(), char_count);
+            match content {
+                Content::Header(s, stys)
+                | Content::Paragraph(s, stys)
+                | Content::Quote(s, stys) => {
+                    styling.add_from_disjoint_other(stys, start);
+                    append(&mut text, &mut char_count, s);
+                }
+                Content::ListItem(marker, s, stys) => {
+                    skipped.push(append(&mut text, &mut char_count, marker));
+                    styling.add_from_disjoint_other(stys, Len::new(text.len(), char_count));
+                    append(&mut text, &mut char_count, s);
                 }
-                styling.add_from_disjoint_other(stys, Len::new(text.len(), char_count));
-                text.push_str(&s);
-                char_count += s.chars().count();
+                Content::Preformatted(s, stys) => {
+                    styling.add_from_disjoint_other(stys, start);
+                    append(&mut text, &mut char_count, s);
+                    // indentation is left out, as an editor would indent
+                    let mut line_start = start;
+                    for line in s.split_inclusive('\n') {
+                        let indent = line.len() - line.trim_start_matches(' ').len();
+                        if indent > 0 {
+                            skipped.push(line_start..line_start + Len::new(indent, indent));
+                        }
+                        line_start += Len::new(line.len(), line.chars().count());
+                    }
+                }
+                Content::TableRow(cells, widths) => {
+                    // cells are separated by a space, padded to line up with
+                    // padding that isn't typed
+                    let mut previous = None;
+                    for ((s, stys), &width) in cells.into_iter().zip(widths) {
+                        if let Some(previous) = previous {
+                            append(&mut text, &mut char_count, " ");
+                            let padding = " ".repeat(previous + 1);
+                            skipped.push(append(&mut text, &mut char_count, &padding));
+                        }
+                        styling.add_from_disjoint_other(stys, Len::new(text.len(), char_count));
+                        append(&mut text, &mut char_count, s);
+                        previous = Some(width - s.chars().count());
+                    }
+                }
+                Content::Image(..) | Content::Note(..) => unreachable!(),
             }
-            Content::Image => {}
-        })
-        .unwrap();
+            paragraphs.push((start..Len::new(text.len(), char_count), kind, align));
+        })?;
+        let images = images
+            .into_iter()
+            .map(|(position, href, alt)| Image {
+                position,
+                alt,
+                data: book.image(chapter, &href).ok(),
+            })
+            .collect();
+        let mut backend = Self::with_content(
+            text,
+            styling,
+            paragraphs,
+            images,
+            config,
+            transliterate,
+            error_mode,
+        );
+        backend.skipped = skipped;
+        backend.notes = shown_notes;
+        backend.skip_forwards();
+        Ok(backend)
+    }
+
+    /// A backend for text that isn't from a book, with a paragraph per line.
+    pub fn from_text(
+        text: String,
+        config: Arc<Config>,
+        transliterate: bool,
+        error_mode: ErrorMode,
+    ) -> Self {
+        let mut paragraphs = Vec::new();
+        let mut start = Len::default();
+        for line in text.split('\n') {
+            let end = start + Len::new(line.len(), line.chars().count());
+            paragraphs.push((start..end, BlockKind::Paragraph, None));
+            start = end + Len::new(1, 1);
+        }
+        let styling = Styling::builder().build();
+        Self::with_content(
+            text,
+            styling,
+            paragraphs,
+            Vec::new(),
+            config,
+            transliterate,
+            error_mode,
+        )
+    }
 
+    fn with_content(
+        text: String,
+        styling: Styling<Len>,
+        paragraphs: Vec<(Range<Len>, BlockKind, Option<Align>)>,
+        images: Vec<Image>,
+        config: Arc<Config>,
+        transliterate: bool,
+        error_mode: ErrorMode,
+    ) -> Self {
+        let mut ranges = Vec::with_capacity(paragraphs.len());
+        let mut kinds = Vec::with_capacity(paragraphs.len());
+        let mut alignments = Vec::with_capacity(paragraphs.len());
+        for (range, kind, align) in paragraphs {
+            ranges.push(range);
+            kinds.push(kind);
+            alignments.push(align);
+        }
         Self {
             text,
             typed: String::new(),
@@ -53,6 +311,21 @@ impl Backend {
             errors: Vec::new(),
             deleted_errors: Vec::new(),
             styling,
+            paragraphs: ranges,
+            kinds,
+            alignments,
+            skipped: Vec::new(),
+            images,
+            notes: Vec::new(),
+            created: Instant::now(),
+            started: None,
+            keystrokes: Vec::new(),
+            mistyped: 0,
+            log: None,
+            config,
+            transliterate,
+            error_mode,
+            pending: String::new(),
         }
     }
 
@@ -76,24 +349,275 @@ impl Backend {
         &self.deleted_errors
     }
 
+    pub fn keystrokes(&self) -> &[Keystroke] {
+        &self.keystrokes
+    }
+
+    pub fn error_mode(&self) -> ErrorMode {
+        self.error_mode
+    }
+
+    /// Time since the first keystroke.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_at(self.created.elapsed())
+    }
+
+    /// Time since the first keystroke when it's `now` since the backend was
+    /// created, on the clock edits are made with in `apply`.
+    pub fn elapsed_at(&self, now: Duration) -> Duration {
+        self.started
+            .map_or(Duration::ZERO, |s| now.saturating_sub(s))
+    }
+
+    /// Starts recording edits, to be collected with `take_log`.
+    pub fn record(&mut self) {
+        self.log.get_or_insert_with(Vec::new);
+    }
+
+    /// Edits recorded so far, with their time since the backend was created.
+    pub fn take_log(&mut self) -> Vec<(Duration, Op)> {
+        self.log.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Number of words completed between `start` and the cursor. A word is
+    /// complete once the whitespace after it, or the end of the text, is
+    /// reached.
+    pub fn words_typed_since(&self, start: Len) -> usize {
+        let typed = &self.text[start.bytes.min(self.cursor.bytes)..self.cursor.bytes];
+        let words = typed.split_whitespace().count();
+        match typed.ends_with(char::is_whitespace) || self.is_finished() {
+            true => words,
+            false => words.saturating_sub(1),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor.bytes == self.text.len()
+    }
+
+    pub fn score(&self) -> Score {
+        Score::new(&self.text, &self.keystrokes, self.errors.len())
+    }
+
+    /// The score's totals, without going through every keystroke, for
+    /// showing as typing goes on.
+    pub fn totals(&self) -> Score {
+        let time = |k: Option<&Keystroke>| k.map_or(Duration::ZERO, |k| k.time);
+        Score::from_totals(
+            time(self.keystrokes.last()) - time(self.keystrokes.first()),
+            self.keystrokes.len(),
+            self.mistyped,
+            self.errors.len(),
+        )
+    }
+
+    /// Ranges of the paragraphs in `text`, excluding the `\n` separating
+    /// them.
+    pub fn paragraphs(&self) -> &[Range<Len>] {
+        &self.paragraphs
+    }
+
+    /// Kind of block each paragraph in `paragraphs` is.
+    pub fn kinds(&self) -> &[BlockKind] {
+        &self.kinds
+    }
+
+    /// Alignment of each paragraph in `paragraphs`.
+    pub fn alignments(&self) -> &[Option<Align>] {
+        &self.alignments
+    }
+
+    /// Ranges of text the cursor moves over without it being typed.
+    pub fn skipped(&self) -> &[Range<Len>] {
+        &self.skipped
+    }
+
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    /// Score of the keystrokes made within a paragraph, once the cursor has
+    /// moved past its end. `None` for paragraphs typed in an earlier session,
+    /// which have no keystrokes.
+    pub fn paragraph_score(&self, paragraph: usize) -> Option<Score> {
+        let range = self.paragraphs.get(paragraph)?;
+        if self.cursor <= range.end && !self.is_finished() {
+            return None;
+        }
+        let keystrokes: Vec<_> = self
+            .keystrokes
+            .iter()
+            .filter(|k| k.position >= range.start && k.position <= range.end)
+            .copied()
+            .collect();
+        if keystrokes.is_empty() {
+            return None;
+        }
+        let uncorrected = self
+            .errors
+            .iter()
+            .filter(|&&e| e >= range.start && e <= range.end)
+            .count();
+        Some(Score::new(&self.text, &keystrokes, uncorrected))
+    }
+
+    /// Moves the cursor to a previously saved position, treating everything
+    /// before it as correctly typed. Positions that don't fall on a character
+    /// boundary of the current text (e.g. the book changed) are ignored.
+    pub fn resume_at(&mut self, cursor: Len) -> bool {
+        let valid = self.text.is_char_boundary(cursor.bytes)
+            && self.text[..cursor.bytes].chars().count() == cursor.chars;
+        if valid {
+            self.typed = self.text[..cursor.bytes].to_owned();
+            self.cursor = cursor;
+            self.cursor_prev = cursor;
+            self.errors.clear();
+            self.pending.clear();
+            self.skip_forwards();
+        }
+        valid
+    }
+
     pub fn clear_per_update_data(&mut self) {
         self.deleted_errors.truncate(0);
     }
 
     pub fn push(&mut self, c: char) {
+        self.apply(Op::Push(c), self.created.elapsed());
+    }
+
+    pub fn pop(&mut self) {
+        self.apply(Op::Pop, self.created.elapsed());
+    }
+
+    pub fn delete_word_backwards(&mut self) {
+        self.apply(Op::DeleteWord, self.created.elapsed());
+    }
+
+    /// Makes an edit as if it happened `time` after the backend was created,
+    /// which lets recorded edits be replayed with their original timing.
+    pub fn apply(&mut self, op: Op, time: Duration) {
+        if let Some(log) = &mut self.log {
+            log.push((time, op));
+        }
+        match op {
+            Op::Push(c) => self.push_impl(c, time),
+            Op::Pop => self.pop_impl(),
+            Op::DeleteWord => self.delete_word_backwards_impl(),
+        }
+    }
+
+    fn push_impl(&mut self, c: char, time: Duration) {
         let Some(goal) = self.text[self.cursor.bytes..].chars().next() else {
             return;
         };
-        self.typed.push(c);
-        if !chars_are_equal_including_unicode_alternatives(goal, c) {
+        let mut keystroke = Keystroke {
+            time: time.saturating_sub(*self.started.get_or_insert(time)),
+            position: self.cursor,
+            expected: goal,
+            typed: c,
+            correct: self.pending.is_empty()
+                && chars_are_equal_including_unicode_alternatives(
+                    self.config.alternatives(),
+                    goal,
+                    c,
+                ),
+        };
+        let ascii = translit::ascii(goal).filter(|_| self.transliterate);
+        if let (false, Some(ascii)) = (keystroke.correct, ascii) {
+            self.pending.push(c);
+            keystroke.correct = ascii.starts_with(&self.pending);
+            if keystroke.correct && self.pending.len() < ascii.len() {
+                // the cursor stays on the character until it's fully typed
+                self.record_keystroke(keystroke);
+                return;
+            }
+        }
+        self.pending.clear();
+        if self.error_mode == ErrorMode::Correct
+            && self.leaves_paragraph(goal)
+            && (!keystroke.correct || self.paragraph_has_errors())
+        {
+            return;
+        }
+        self.record_keystroke(keystroke);
+        if self.error_mode == ErrorMode::Stop && !keystroke.correct {
+            return;
+        }
+        // `typed` holds a character for every character of text
+        self.typed.push(match keystroke.correct {
+            true => goal,
+            false => c,
+        });
+        if !keystroke.correct {
             self.errors.push(self.cursor);
         }
         self.cursor_prev = self.cursor;
         self.cursor.bytes += goal.len_utf8();
         self.cursor.chars += 1;
+        self.skip_forwards();
     }
 
-    pub fn pop(&mut self) {
+    fn record_keystroke(&mut self, keystroke: Keystroke) {
+        self.mistyped += usize::from(!keystroke.correct);
+        self.keystrokes.push(keystroke);
+    }
+
+    /// Moves the cursor over any skipped text it's at the start of, as if it
+    /// were typed correctly.
+    fn skip_forwards(&mut self) {
+        let idx = self.skipped.partition_point(|r| r.start < self.cursor);
+        if let Some(range) = self.skipped.get(idx).filter(|r| r.start == self.cursor) {
+            self.typed
+                .push_str(&self.text[range.start.bytes..range.end.bytes]);
+            self.cursor = range.end;
+        }
+    }
+
+    /// Moves the cursor back over any skipped text it's at the end of,
+    /// returning whether there's anything before it left to delete. Skipped
+    /// text that starts the chapter is kept, so the cursor stays after it.
+    fn skip_backwards(&mut self) -> bool {
+        let idx = self.skipped.partition_point(|r| r.end < self.cursor);
+        if let Some(range) = self.skipped.get(idx).filter(|r| r.end == self.cursor) {
+            if range.start.bytes == 0 {
+                return false;
+            }
+            let len = range.end.bytes - range.start.bytes;
+            self.typed.truncate(self.typed.len() - len);
+            self.cursor = range.start;
+        }
+        true
+    }
+
+    /// Whether typing `goal` at the cursor finishes the paragraph it's in,
+    /// either by moving on to the next one or by reaching the end of the text.
+    fn leaves_paragraph(&self, goal: char) -> bool {
+        let idx = self.paragraphs.partition_point(|p| p.end < self.cursor);
+        let at_end = match self.paragraphs.get(idx) {
+            Some(p) => self.cursor >= p.end,
+            None => true,
+        };
+        at_end || self.cursor.bytes + goal.len_utf8() == self.text.len()
+    }
+
+    fn paragraph_has_errors(&self) -> bool {
+        let idx = self.paragraphs.partition_point(|p| p.end < self.cursor);
+        let start = self.paragraphs.get(idx).map_or(Len::default(), |p| p.start);
+        self.errors.last().is_some_and(|&e| e >= start)
+    }
+
+    fn pop_impl(&mut self) {
+        if self.pending.pop().is_some() {
+            return;
+        }
+        if !self.skip_backwards() {
+            return;
+        }
         let Some(typed) = self.typed.chars().last() else {
             return;
         };
@@ -101,7 +625,11 @@ impl Backend {
         self.delete_backwards_impl(Len::new(text.len_utf8(), 1), Len::new(typed.len_utf8(), 1));
     }
 
-    pub fn delete_word_backwards(&mut self) {
+    fn delete_word_backwards_impl(&mut self) {
+        self.pending.clear();
+        if !self.skip_backwards() {
+            return;
+        }
         let mut found_nonwhitespace = false;
         let [typed, text] = self
             .typed
@@ -132,6 +660,15 @@ impl Backend {
     }
 }
 
+/// Appends `s` to `text`, whose length in characters is `char_count`,
+/// returning the range it takes up.
+fn append(text: &mut String, char_count: &mut usize, s: &str) -> Range<Len> {
+    let start = Len::new(text.len(), *char_count);
+    text.push_str(s);
+    *char_count += s.chars().count();
+    start..Len::new(text.len(), *char_count)
+}
+
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct Len {
     pub bytes: usize,
@@ -192,12 +729,191 @@ impl std::ops::SubAssign<Self> for Len {
     }
 }
 
-fn chars_are_equal_including_unicode_alternatives(expected: char, got: char) -> bool {
+fn chars_are_equal_including_unicode_alternatives(
+    alternatives: &[(char, Vec<char>)],
+    expected: char,
+    got: char,
+) -> bool {
     if expected == got {
         true
-    } else if let Some(alts) = ALTERNATIVES.iter().find(|x| x.0 == got) {
+    } else if let Some(alts) = alternatives.iter().find(|x| x.0 == got) {
         alts.1.contains(&expected)
     } else {
         false
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::html::Html;
+
+    /// A backend for the first chapter of an HTML document.
+    pub(crate) fn from_html(xml: &str, notes: Notes) -> Backend {
+        let mut book = Html::from_data("test.html".into(), xml.as_bytes()).unwrap();
+        let config = Arc::new(Config::default());
+        Backend::new(
+            &mut book,
+            0,
+            Quotes::Keep,
+            notes,
+            config,
+            false,
+            ErrorMode::Advance,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn it_keeps_skipped_text_at_the_start_of_a_chapter() {
+        let xml = "<html><body><ul><li>item</li></ul></body></html>";
+        let mut backend = from_html(xml, Notes::Skip);
+        assert_eq!(backend.text(), "• item");
+        let start = Len::new("• ".len(), 2);
+        assert_eq!(backend.cursor(), start);
+
+        backend.pop();
+        assert_eq!(backend.cursor(), start);
+        backend.delete_word_backwards();
+        assert_eq!(backend.cursor(), start);
+
+        backend.push('i');
+        backend.push('t');
+        backend.delete_word_backwards();
+        assert_eq!(backend.cursor(), start);
+        backend.push('i');
+        backend.pop();
+        backend.pop();
+        assert_eq!(backend.cursor(), start);
+        assert!(backend.errors().is_empty());
+    }
+
+    fn typing(text: &str, transliterate: bool, error_mode: ErrorMode) -> Backend {
+        let config = Arc::new(Config::default());
+        Backend::from_text(text.to_owned(), config, transliterate, error_mode)
+    }
+
+    #[test]
+    fn it_stops_on_errors() {
+        let mut backend = typing("ab", false, ErrorMode::Stop);
+        backend.push('x');
+        assert_eq!(backend.cursor(), Len::new(0, 0));
+        assert!(backend.errors().is_empty());
+        assert!(!backend.keystrokes()[0].correct);
+        backend.push('a');
+        backend.push('b');
+        assert!(backend.is_finished());
+        assert_eq!(backend.score().errors, 1);
+    }
+
+    #[test]
+    fn it_makes_errors_be_corrected_before_leaving_a_paragraph() {
+        let mut backend = typing("ab\ncd", false, ErrorMode::Correct);
+        backend.push('a');
+        backend.push('x');
+        backend.push('\n');
+        assert_eq!(backend.cursor(), Len::new(2, 2));
+        // backspace removes the error, not the refused line break
+        backend.pop();
+        assert_eq!(backend.cursor(), Len::new(1, 1));
+        assert!(backend.errors().is_empty());
+        backend.push('b');
+        backend.push('\n');
+        assert_eq!(backend.cursor(), Len::new(3, 3));
+
+        // nor can the text be finished with an error
+        backend.push('c');
+        backend.push('x');
+        assert_eq!(backend.cursor(), Len::new(4, 4));
+        assert!(!backend.is_finished());
+        backend.push('d');
+        assert!(backend.is_finished());
+        assert!(backend.errors().is_empty());
+    }
+
+    #[test]
+    fn it_types_transliterations() {
+        let mut backend = typing("aßb", true, ErrorMode::Advance);
+        backend.push('a');
+        backend.push('s');
+        // the cursor waits for the rest of the transliteration
+        assert_eq!(backend.cursor(), Len::new(1, 1));
+        backend.push('s');
+        assert_eq!(backend.cursor(), Len::new(3, 2));
+        backend.push('b');
+        assert!(backend.is_finished());
+        assert!(backend.errors().is_empty());
+    }
+
+    #[test]
+    fn it_pops_half_typed_transliterations() {
+        let mut backend = typing("ßa", true, ErrorMode::Advance);
+        backend.push('s');
+        backend.pop();
+        assert_eq!(backend.cursor(), Len::new(0, 0));
+        backend.push('s');
+        backend.push('s');
+        assert_eq!(backend.cursor(), Len::new(2, 1));
+        assert!(backend.errors().is_empty());
+
+        // a wrong key partway through is an error for the whole character
+        let mut backend = typing("ßa", true, ErrorMode::Advance);
+        backend.push('s');
+        backend.push('x');
+        assert_eq!(backend.cursor(), Len::new(2, 1));
+        assert_eq!(backend.errors(), [Len::new(0, 0)]);
+        backend.pop();
+        assert_eq!(backend.cursor(), Len::new(0, 0));
+        assert!(backend.errors().is_empty());
+    }
+
+    #[test]
+    fn it_places_notes_after_the_paragraph_referring_to_them() {
+        let xml = r##"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<p>One<a epub:type="noteref" href="#n1">1</a>.</p>
+<p>Two.</p>
+<p>Three<sup><a id="r2" href="#n2">2</a></sup></p>
+<aside id="n1" epub:type="footnote"><p>First.</p></aside>
+<p id="n2"><a href="#r2">2</a> Second.</p>
+</body></html>"##;
+        let backend = from_html(xml, Notes::Panel);
+        assert_eq!(backend.text(), "One.\nTwo.\nThree");
+        let paragraphs = backend.paragraphs();
+        let notes: Vec<_> = backend
+            .notes()
+            .iter()
+            .map(|n| (n.position, n.text.as_str()))
+            .collect();
+        assert_eq!(
+            notes,
+            [
+                (paragraphs[0].end, "First."),
+                (paragraphs[2].end, "Second.")
+            ]
+        );
+    }
+
+    #[test]
+    fn it_scores_only_paragraphs_typed_this_session() {
+        let mut backend = typing("ab\ncd", false, ErrorMode::Advance);
+        assert!(backend.resume_at(Len::new(3, 3)));
+        backend.push('c');
+        backend.push('d');
+        assert!(backend.paragraph_score(0).is_none());
+        assert_eq!(backend.paragraph_score(1).unwrap().keystrokes, 2);
+    }
+
+    #[test]
+    fn it_keeps_totals_of_the_score() {
+        let mut backend = typing("abc", false, ErrorMode::Advance);
+        for c in ['a', 'x', 'c'] {
+            backend.push(c);
+        }
+        backend.pop();
+        let (totals, score) = (backend.totals(), backend.score());
+        assert_eq!(totals.keystrokes, score.keystrokes);
+        assert_eq!(totals.errors, score.errors);
+        assert_eq!(totals.uncorrected, score.uncorrected);
+        assert_eq!(totals.elapsed, score.elapsed);
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    epub::{
+        finish_block, push_preformatted, push_text, Align, Author, Content, Epub, TextOptions,
+        TocEntry,
+    },
+    fb2,
+    html::Html,
+    markdown, plaintext,
+    style::{Style, Styling},
+};
+
+/// Extensions of the files that can be opened as books.
+pub const EXTENSIONS: &[&str] = &[
+    "epub", "txt", "md", "markdown", "fb2", "html", "htm", "xhtml",
+];
+
+/// A book in any of the supported formats.
+pub trait Book {
+    fn title(&self) -> &str;
+
+    fn authors(&self) -> &[Author];
+
+    fn author(&self) -> Option<&Author> {
+        self.authors().first()
+    }
+
+    /// Identifies the book for saving state, regardless of where it is.
+    fn identifier(&self) -> &str;
+
+    /// Absolute path of the file the book was read from.
+    fn path(&self) -> &Path;
+
+    fn chapters(&self) -> &[TocEntry];
+
+    fn chapter_count(&self) -> usize {
+        self.chapters().len()
+    }
+
+    /// Size of each chapter, as a rough measure of how much of the book it
+    /// makes up.
+    fn chapter_sizes(&mut self) -> Vec<u64>;
+
+    /// Reads the content of a chapter.
+    fn traverse(
+        &mut self,
+        entry: usize,
+        options: &TextOptions,
+        cb: &mut dyn FnMut(Content<'_>, Option<Align>),
+    ) -> anyhow::Result<()>;
+
+    /// Raw data of an image referenced from a chapter.
+    fn image(&mut self, entry: usize, href: &str) -> anyhow::Result<Vec<u8>>;
+}
+
+/// Opens a book, choosing the format by the file's extension.
+pub fn open(path: &Path) -> anyhow::Result<Box<dyn Book>> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    Ok(match extension.as_deref() {
+        Some("epub") => Box::new(Epub::from_path(path)?),
+        Some("txt") => Box::new(plaintext::load(path)?),
+        Some("md" | "markdown") => Box::new(markdown::load(path)?),
+        Some("fb2") => Box::new(fb2::load(path)?),
+        Some("html" | "htm" | "xhtml") => Box::new(Html::from_path(path)?),
+        _ => anyhow::bail!("unsupported book format: `{}`", path.display()),
+    })
+}
+
+pub fn is_book(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// A block of a chapter in a format that's parsed as a whole when opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Block {
+    Header(Vec<(String, Style)>),
+    Paragraph(Vec<(String, Style)>),
+    Quote(Vec<(String, Style)>),
+    /// Code, keeping its line breaks and indentation.
+    Preformatted(String),
+    /// Image source and its alt text.
+    Image(String, Option<String>),
+}
+
+/// A book in one of the simpler formats, read into memory up front.
+pub(crate) struct Parsed {
+    pub path: PathBuf,
+    pub identifier: String,
+    pub title: String,
+    pub authors: Vec<Author>,
+    pub chapters: Vec<TocEntry>,
+    pub content: Vec<Vec<Block>>,
+    /// Images embedded in the book, by the source they're referred to with.
+    pub images: HashMap<String, Vec<u8>>,
+}
+
+impl Parsed {
+    /// A book of `chapters` with their names, depths and blocks.
+    pub fn new(
+        path: &Path,
+        identifier: String,
+        title: String,
+        authors: Vec<Author>,
+        chapters: Vec<(String, usize, Vec<Block>)>,
+    ) -> Self {
+        let mut entries = Vec::new();
+        let mut content = Vec::new();
+        for (i, (name, depth, blocks)) in chapters.into_iter().enumerate() {
+            entries.push(TocEntry::new(name, i, depth));
+            content.push(blocks);
+        }
+        Self {
+            path: fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()),
+            identifier,
+            title,
+            authors,
+            chapters: entries,
+            content,
+            images: HashMap::new(),
+        }
+    }
+}
+
+impl Book for Parsed {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn authors(&self) -> &[Author] {
+        &self.authors
+    }
+
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn chapters(&self) -> &[TocEntry] {
+        &self.chapters
+    }
+
+    fn chapter_sizes(&mut self) -> Vec<u64> {
+        let runs_len = |runs: &[(String, Style)]| runs.iter().map(|(s, _)| s.len()).sum::<usize>();
+        self.content
+            .iter()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .map(|block| match block {
+                        Block::Header(runs) | Block::Paragraph(runs) | Block::Quote(runs) => {
+                            runs_len(runs) as u64
+                        }
+                        Block::Preformatted(s) => s.len() as u64,
+                        Block::Image(..) => 0,
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn traverse(
+        &mut self,
+        entry: usize,
+        options: &TextOptions,
+        cb: &mut dyn FnMut(Content<'_>, Option<Align>),
+    ) -> anyhow::Result<()> {
+        for block in &self.content[entry] {
+            let runs = match block {
+                Block::Header(runs) | Block::Paragraph(runs) | Block::Quote(runs) => runs,
+                Block::Preformatted(s) => {
+                    let mut text = String::new();
+                    let mut styling = Styling::builder();
+                    push_preformatted(&mut text, &mut styling, s, Style::MONOSPACE, options);
+                    finish_block(&mut text, options);
+                    if !text.is_empty() {
+                        cb(Content::Preformatted(&text, styling.build()), None);
+                    }
+                    continue;
+                }
+                Block::Image(src, alt) => {
+                    cb(Content::Image(src, alt.as_deref()), None);
+                    continue;
+                }
+            };
+            let mut text = String::new();
+            let mut styling = Styling::builder();
+            for (s, style) in runs {
+                push_text(&mut text, &mut styling, s, *style, options);
+            }
+            finish_block(&mut text, options);
+            if text.is_empty() {
+                continue;
+            }
+            let styling = styling.build();
+            let content = match block {
+                Block::Header(_) => Content::Header(&text, styling),
+                Block::Quote(_) => Content::Quote(&text, styling),
+                _ => Content::Paragraph(&text, styling),
+            };
+            cb(content, None);
+        }
+        Ok(())
+    }
+
+    fn image(&mut self, _entry: usize, href: &str) -> anyhow::Result<Vec<u8>> {
+        if let Some(data) = self.images.get(href) {
+            return Ok(data.clone());
+        }
+        // anything else is a file next to the book
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        Ok(fs::read(dir.join(href))?)
+    }
+}
+
+/// Identifier of a book without one of its own, from a hash of its contents.
+pub(crate) fn content_identifier(kind: &str, data: &[u8]) -> String {
+    // FNV-1a, which unlike the standard library's hasher is stable
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in data {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{kind}:{hash:016x}")
+}
+
+/// Name of a book from its file name, for formats without a title.
+pub(crate) fn title_from_path(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,195 @@
+//! User configuration, read from `config.ini` in the platform's config
+//! directory (e.g. `~/.config/typepub/config.ini`).
+//!
+//! ```ini
+//! # characters in the book and what to type instead
+//! [replacements]
+//! – = -
+//! æ = ae
+//! ﬁ = fi
+//! × = x
+//!
+//! # a typed character and the characters in the book it's accepted for
+//! [alternatives]
+//! - = – —
+//! ```
+//!
+//! Entries extend the built-in tables, replacing any built-in entry for the
+//! same character. Values can be wrapped in double quotes to keep surrounding
+//! whitespace, and `""` removes a character from the text altogether.
+
+use std::{fs, io, path::PathBuf};
+
+use anyhow::Context as _;
+
+use crate::book::content_identifier;
+
+#[rustfmt::skip]
+const REPLACEMENTS: &[(char, &str)] = &[
+    ('—', "--"),
+    ('…', "..."),
+];
+
+#[rustfmt::skip]
+const ALTERNATIVES: &[(char, &[char])] = &[
+    ('\'', &['‘', '’']),
+    ('\"', &['“', '”']),
+    (' ',  &[' '])
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    replaced: Vec<char>,
+    replacements: Vec<String>,
+    alternatives: Vec<(char, Vec<char>)>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Section {
+    Replacements,
+    Alternatives,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            replaced: REPLACEMENTS.iter().map(|&(c, _)| c).collect(),
+            replacements: REPLACEMENTS.iter().map(|&(_, s)| s.to_owned()).collect(),
+            alternatives: ALTERNATIVES
+                .iter()
+                .map(|&(c, alts)| (c, alts.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the user's config file, falling back to the defaults if there
+    /// isn't one.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = config_path()?;
+        match fs::read_to_string(&path) {
+            Ok(data) => Self::parse(&data).with_context(|| format!("in {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn parse(data: &str) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        let mut section = None;
+        for (i, line) in data.lines().enumerate() {
+            // non-ascii whitespace like no-break spaces can be keys and values
+            let line = line.trim_matches(|c: char| c.is_ascii_whitespace());
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = match name.trim() {
+                    "replacements" => Some(Section::Replacements),
+                    "alternatives" => Some(Section::Alternatives),
+                    _ => anyhow::bail!("line {}: unknown section `{name}`", i + 1),
+                };
+                continue;
+            }
+
+            let (key, value) = parse_entry(line).with_context(|| format!("line {}", i + 1))?;
+            match section {
+                Some(Section::Replacements) => config.set_replacement(key, value),
+                Some(Section::Alternatives) => {
+                    let alts = value.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+                    config.set_alternatives(key, alts);
+                }
+                None => anyhow::bail!("line {}: entry outside of a section", i + 1),
+            }
+        }
+        Ok(config)
+    }
+
+    fn set_replacement(&mut self, c: char, replacement: String) {
+        match self.replaced.iter().position(|&r| r == c) {
+            Some(i) => self.replacements[i] = replacement,
+            None => {
+                self.replaced.push(c);
+                self.replacements.push(replacement);
+            }
+        }
+    }
+
+    fn set_alternatives(&mut self, c: char, alts: Vec<char>) {
+        match self.alternatives.iter_mut().find(|(a, _)| *a == c) {
+            Some(entry) => entry.1 = alts,
+            None => self.alternatives.push((c, alts)),
+        }
+    }
+
+    /// Characters to replace in the text, and what each is replaced with.
+    pub fn replacements(&self) -> (&[char], &[String]) {
+        (&self.replaced, &self.replacements)
+    }
+
+    /// Typed characters and the characters in the text they're accepted for.
+    pub fn alternatives(&self) -> &[(char, Vec<char>)] {
+        &self.alternatives
+    }
+
+    /// Identifies the tables, to tell whether they've changed since a log was
+    /// recorded.
+    pub fn fingerprint(&self) -> String {
+        let tables = format!(
+            "{:?} {:?} {:?}",
+            self.replaced, self.replacements, self.alternatives
+        );
+        content_identifier("config", tables.as_bytes())
+    }
+}
+
+pub fn config_path() -> anyhow::Result<PathBuf> {
+    let mut path = dirs::config_dir().context("could not locate config directory")?;
+    path.push("typepub");
+    path.push("config.ini");
+    Ok(path)
+}
+
+fn parse_entry(line: &str) -> anyhow::Result<(char, String)> {
+    // split after the first character, so that `=` can be a key
+    let mut chars = line.chars();
+    let key = chars.next().context("missing key")?;
+    let value = chars
+        .as_str()
+        .trim_start_matches(|c: char| c.is_ascii_whitespace())
+        .strip_prefix('=')
+        .context("expected `<character> = <value>`")?
+        .trim_matches(|c: char| c.is_ascii_whitespace());
+    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => quoted,
+        None => value,
+    };
+    Ok((key, value.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_extends_and_overrides_defaults() {
+        let config = Config::parse(
+            "# comment\n[replacements]\n– = -\nﬁ = fi\n— = \" - \"\n\n[alternatives]\n= = ＝\n' = ’\n",
+        )
+        .unwrap();
+        let (replaced, replacements) = config.replacements();
+        assert_eq!(replaced, &['—', '…', '–', 'ﬁ']);
+        assert_eq!(replacements, &[" - ", "...", "-", "fi"]);
+        assert!(config.alternatives().contains(&('\'', vec!['’'])));
+        assert!(config.alternatives().contains(&('=', vec!['＝'])));
+        assert!(config.alternatives().contains(&(' ', vec![' '])));
+    }
+
+    #[test]
+    fn it_rejects_malformed_entries() {
+        assert!(Config::parse("– = -\n").is_err());
+        assert!(Config::parse("[replacements]\nae\n").is_err());
+        assert!(Config::parse("[colours]\n").is_err());
+    }
+}
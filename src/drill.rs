@@ -0,0 +1,159 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    path::PathBuf,
+};
+
+use crate::{
+    book,
+    config::Config,
+    epub::{Content, Directory, TextOptions},
+    notes::Notes,
+    quotes::Quotes,
+    stats::Stats,
+};
+
+/// Sentences shorter or longer than this, in characters, are passed over.
+const SENTENCE_LEN: RangeInclusive<usize> = 30..=240;
+/// Number of the weakest bigrams a drill concentrates on.
+const WEAK_BIGRAMS: usize = 20;
+
+/// Sentences to practise the weakest bigrams with.
+pub struct Drill {
+    /// The sentences, one per line.
+    pub text: String,
+    /// The book most of the sentences are from.
+    pub book: PathBuf,
+}
+
+impl Drill {
+    /// Picks the `count` sentences from the books in `library` that are
+    /// densest in the weakest bigrams. `None` if there aren't any weak bigrams
+    /// yet, or any sentences with them.
+    pub fn generate(
+        library: &Directory,
+        stats: &Stats,
+        config: &Config,
+        count: usize,
+    ) -> anyhow::Result<Option<Self>> {
+        let weak: HashMap<(char, char), f64> = stats
+            .weakest_bigrams()
+            .into_iter()
+            .take(WEAK_BIGRAMS)
+            .collect();
+        if weak.is_empty() {
+            return Ok(None);
+        }
+
+        let options = TextOptions {
+            replacements: config.replacements(),
+            quotes: Quotes::Keep,
+            notes: Notes::Skip,
+        };
+        let mut candidates = Vec::new();
+        let books = library.books()?;
+        for (i, path) in books.iter().enumerate() {
+            let mut book = match book::open(path) {
+                Ok(book) => book,
+                Err(e) => {
+                    eprintln!("failed to parse: {e}");
+                    continue;
+                }
+            };
+            for chapter in 0..book.chapter_count() {
+                let _ = book.traverse(chapter, &options, &mut |content, _align| {
+                    if let Content::Paragraph(s, _) | Content::Quote(s, _) = content {
+                        for sentence in sentences(s) {
+                            let score = density(sentence, &weak);
+                            if score > 0.0 {
+                                candidates.push((score, sentence.to_owned(), i));
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        // sentences repeated anywhere in the library are only drilled once
+        let mut seen = HashSet::new();
+        candidates.retain(|(_, s, _)| seen.insert(s.clone()));
+        candidates.truncate(count);
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let mut from_book = vec![0; books.len()];
+        for (_, _, i) in &candidates {
+            from_book[*i] += 1;
+        }
+        let book = (0..books.len()).max_by_key(|&i| from_book[i]).unwrap();
+        let sentences: Vec<_> = candidates.into_iter().map(|(_, s, _)| s).collect();
+        Ok(Some(Self {
+            text: sentences.join("\n"),
+            book: books[book].clone(),
+        }))
+    }
+}
+
+/// Splits a paragraph into sentences of a length worth typing.
+fn sentences(paragraph: &str) -> impl Iterator<Item = &str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = paragraph.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        // closing quotes and brackets belong to the sentence they end
+        while let Some(&(_, c)) = chars.peek() {
+            match matches!(c, '"' | '\'' | '’' | '”' | ')' | ']') {
+                true => chars.next(),
+                false => break,
+            };
+        }
+        match chars.peek() {
+            Some(&(end, c)) if c.is_whitespace() => {
+                sentences.push(&paragraph[start..end]);
+                start = end;
+            }
+            Some(_) => {}
+            None => {
+                sentences.push(&paragraph[start..]);
+                start = paragraph.len();
+            }
+        }
+    }
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.contains('\n') && SENTENCE_LEN.contains(&s.chars().count()))
+}
+
+/// Weakness of the weak bigrams in `sentence`, per character.
+fn density(sentence: &str, weak: &HashMap<(char, char), f64>) -> f64 {
+    let chars: Vec<char> = sentence.chars().collect();
+    let total: f64 = chars
+        .windows(2)
+        .filter_map(|w| weak.get(&(w[0], w[1])))
+        .sum();
+    total / chars.len() as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_splits_sentences() {
+        let paragraph = "“Is this sentence long enough to drill?” she asked. Too short. \
+            Yes, it is long enough to be typed again! But not this one";
+        let found: Vec<_> = sentences(paragraph).collect();
+        assert_eq!(
+            found,
+            [
+                "“Is this sentence long enough to drill?”",
+                "Yes, it is long enough to be typed again!"
+            ]
+        );
+    }
+}
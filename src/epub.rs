@@ -1,6 +1,9 @@
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     fs,
     io::{self, Read},
+    ops::Range,
     path::{Path, PathBuf},
 };
 
@@ -11,6 +14,10 @@ use url::Url;
 
 use crate::{
     backend::Len,
+    book::{self, Book},
+    notes::{self, Notes},
+    quotes::{self, Quotes},
+    repair,
     style::{Style, Styling},
 };
 
@@ -24,6 +31,14 @@ pub fn ebook_directory() -> anyhow::Result<std::path::PathBuf> {
     // fs::read_dir(dir).map_err(Into::into)
 }
 
+pub fn state_directory() -> anyhow::Result<std::path::PathBuf> {
+    let mut dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .context("could not locate local state directory")?;
+    dir.push("typepub");
+    Ok(dir)
+}
+
 struct EpubArchive {
     archive: zip::ZipArchive<io::BufReader<fs::File>>,
     manifest: Manifest,
@@ -31,6 +46,7 @@ struct EpubArchive {
 }
 
 pub struct Epub {
+    path: PathBuf,
     archive: EpubArchive,
     metadata: Metadata,
     spine: Spine,
@@ -41,24 +57,6 @@ impl Epub {
     pub fn from_path(path: &Path) -> anyhow::Result<Self> {
         EpubPreview::from_file(path)?.full()
     }
-
-    pub fn name(&self) -> &str {
-        &self.metadata.title
-    }
-
-    pub fn author(&self) -> Option<&Author> {
-        self.metadata.creators.first()
-    }
-
-    pub fn chapters(
-        &self,
-    ) -> impl Iterator<Item = &TocEntry> + DoubleEndedIterator + ExactSizeIterator {
-        self.toc.0.iter()
-    }
-
-    pub fn chapter_count(&self) -> usize {
-        self.toc.0.len()
-    }
 }
 
 #[derive(Debug, Clone)]
@@ -147,6 +145,15 @@ pub struct TocEntry {
 }
 
 impl TocEntry {
+    pub(crate) fn new(name: String, idx: usize, depth: usize) -> Self {
+        Self {
+            name,
+            fragment: None,
+            idx,
+            depth,
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -250,7 +257,7 @@ impl Toc {
     fn parse_v2(archive: &mut EpubArchive, spine: &Spine, ncx_idx: usize) -> anyhow::Result<Self> {
         let data = archive.retrieve(ncx_idx)?;
         // panic!("{}", data);
-        let xml = roxmltree::Document::parse(&data).unwrap();
+        let xml = roxmltree::Document::parse(&data).context("failed to parse the NCX")?;
 
         let nav_map = xml
             .root_element()
@@ -344,6 +351,7 @@ impl Toc {
 }
 
 struct EpubPreview {
+    path: PathBuf,
     archive: zip::ZipArchive<io::BufReader<std::fs::File>>,
     root: PathBuf,
     metadata: Metadata,
@@ -420,7 +428,8 @@ impl EpubPreview {
         use io::Read as _;
 
         // let ts = std::time::Instant::now();
-        let fd = File::open(path)?;
+        let fd = File::open(&path)?;
+        let path = fs::canonicalize(&path).unwrap_or_else(|_| path.as_ref().to_owned());
         let mut archive = zip::ZipArchive::new(std::io::BufReader::new(fd))?;
 
         let mut buf = String::new();
@@ -468,6 +477,7 @@ impl EpubPreview {
         // println!("3: {:?}", ts.elapsed());
 
         Ok(Self {
+            path,
             archive,
             root,
             version,
@@ -478,6 +488,7 @@ impl EpubPreview {
 
     fn full(self) -> anyhow::Result<Epub> {
         let Self {
+            path,
             archive,
             root,
             version,
@@ -519,6 +530,7 @@ impl EpubPreview {
         };
 
         Ok(Epub {
+            path,
             archive,
             metadata,
             spine,
@@ -543,6 +555,21 @@ impl EpubArchive {
         Ok(data)
     }
 
+    fn size(&mut self, item: usize) -> anyhow::Result<u64> {
+        let item = &self.manifest.0[item];
+        let abs_path = self.name_in_archive(&item.path);
+        Ok(self.archive.by_name(&abs_path)?.size())
+    }
+
+    fn retrieve_bytes(&mut self, item: usize) -> anyhow::Result<Vec<u8>> {
+        let item = &self.manifest.0[item];
+        let abs_path = self.name_in_archive(&item.path);
+        let mut data = Vec::new();
+        let mut file = self.archive.by_name(&abs_path)?;
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
     fn retrieve_idx(&mut self, item: usize) -> anyhow::Result<String> {
         let item = &self.manifest.0[item];
         let abs_path = self.name_in_archive(&item.path);
@@ -607,143 +634,418 @@ impl simplecss::Element for XmlNode<'_, '_> {
 
 #[derive(Debug, Clone, Copy)]
 enum CssAttribute {
-    Style(Style),
+    /// Styles that are set, or with `false` cleared.
+    Style(Style, bool),
     Align(Align),
+    Transform(TextTransform),
 }
 
-impl Epub {
-    pub fn traverse(
-        &mut self,
-        entry: usize,
-        replacements: &(&[char], &[&str]),
-        mut cb: impl FnMut(Content<'_>, Option<Align>),
-    ) -> anyhow::Result<(&str, &str)> {
-        let item_idx = self.spine.0[self.toc.0[entry].idx];
-        let mut data = self.archive.retrieve(item_idx)?;
-
-        let xml = match roxmltree::Document::parse(&data) {
-            Ok(x) => x,
-            Err(roxmltree::Error::UnknownEntityReference(name, _)) => {
-                let (needle, replacement) = match name.as_ref() {
-                    "nbsp" => ("&nbsp;", " "),
-                    _ => panic!(),
-                };
-
-                data = data.replace(needle, replacement);
-                roxmltree::Document::parse(&data).unwrap()
+impl CssAttribute {
+    /// The attribute a declaration sets, if it's one that's recognised.
+    fn parse(dec: &simplecss::Declaration) -> Option<Self> {
+        let value = dec.value.trim().to_ascii_lowercase();
+        let value = value.as_str();
+        Some(match dec.name {
+            "font-style" => match value {
+                "normal" => Self::Style(Style::ITALIC, false),
+                _ if value == "italic" || value.starts_with("oblique") => {
+                    Self::Style(Style::ITALIC, true)
+                }
+                _ => return None,
+            },
+            "font-weight" => match value {
+                "bold" | "bolder" => Self::Style(Style::BOLD, true),
+                "normal" | "lighter" => Self::Style(Style::BOLD, false),
+                _ => Self::Style(Style::BOLD, value.parse::<usize>().ok()? > 400),
+            },
+            "font-variant" | "font-variant-caps" => match value {
+                "normal" => Self::Style(Style::SMALL_CAPS, false),
+                _ if value.contains("small-caps") => Self::Style(Style::SMALL_CAPS, true),
+                _ => return None,
+            },
+            "text-decoration" | "text-decoration-line" => {
+                let decorations = Style::UNDERLINE | Style::STRIKETHROUGH;
+                match value {
+                    "none" => Self::Style(decorations, false),
+                    _ => {
+                        let mut style = Style::empty();
+                        for word in value.split_ascii_whitespace() {
+                            match word {
+                                "underline" => style |= Style::UNDERLINE,
+                                "line-through" => style |= Style::STRIKETHROUGH,
+                                _ => {}
+                            }
+                        }
+                        match style.is_empty() {
+                            true => return None,
+                            false => Self::Style(style, true),
+                        }
+                    }
+                }
             }
-            Err(e) => panic!("{e}"),
-        };
+            "vertical-align" => match value {
+                "super" => Self::Style(Style::SUPERSCRIPT, true),
+                "sub" => Self::Style(Style::SUBSCRIPT, true),
+                "baseline" => Self::Style(Style::SUPERSCRIPT | Style::SUBSCRIPT, false),
+                _ => return None,
+            },
+            "font-family" => {
+                let monospace = ["monospace", "mono", "courier", "consolas", "menlo"]
+                    .into_iter()
+                    .any(|font| value.contains(font));
+                Self::Style(Style::MONOSPACE, monospace)
+            }
+            "font-size" => Self::Style(Style::SMALL, is_small(value)?),
+            "text-align" => Self::Align(match value {
+                "left" | "start" | "justify" => Align::Left,
+                "center" => Align::Center,
+                "right" | "end" => Align::Right,
+                // `inherit`, and anything unknown
+                _ => return None,
+            }),
+            "text-transform" => Self::Transform(match value {
+                "uppercase" => TextTransform::Uppercase,
+                "lowercase" => TextTransform::Lowercase,
+                "capitalize" => TextTransform::Capitalize,
+                "none" => TextTransform::None,
+                _ => return None,
+            }),
+            _ => return None,
+        })
+    }
+}
 
-        let (head, body) = {
-            let mut containers = xml
-                .root_element()
-                .children()
-                .filter(roxmltree::Node::is_element);
-            (
-                containers.next().context("missing head")?,
-                containers.next().context("missing body")?,
-            )
-        };
+/// Whether a `font-size` is smaller than the text around it, if that's
+/// known.
+fn is_small(size: &str) -> Option<bool> {
+    match size {
+        "smaller" | "small" | "x-small" | "xx-small" => return Some(true),
+        "larger" | "medium" | "large" | "x-large" | "xx-large" | "xxx-large" => return Some(false),
+        _ => {}
+    }
+    // sizes in pixels or points depend on the size they're compared to
+    let (number, scale) = match size.strip_suffix('%') {
+        Some(number) => (number, 100.0),
+        None => (size.strip_suffix("rem").or(size.strip_suffix("em"))?, 1.0),
+    };
+    Some(number.trim().parse::<f64>().ok()? < scale)
+}
 
-        let mut raw_stylesheets = Vec::new();
-        for node in head.children().filter(Node::is_element) {
-            match node.tag_name().name() {
-                "link" if node.attribute("rel") == Some("stylesheet") => {
-                    let href = node.attribute("href").unwrap();
-                    let css_item = self.archive.resolve_hyperlink(item_idx, href)?;
-                    let css = self.archive.retrieve_idx(css_item)?;
-                    raw_stylesheets.push(css);
-                }
-                "style" if matches!(node.attribute("type"), Some("text/css") | None) => {
-                    raw_stylesheets.push(node.text().context("style tag without text")?.to_owned());
+/// A recognised declaration of a stylesheet rule.
+struct Declared {
+    /// Index of the rule in the stylesheet.
+    rule: usize,
+    important: bool,
+    attribute: CssAttribute,
+}
+
+/// What's needed to read a document, besides the node being read.
+struct Reader<'a> {
+    options: &'a TextOptions<'a>,
+    styles: &'a StyleSheet<'a>,
+    rules: &'a [Declared],
+    /// Text of the notes that references link to, by the links' `href`.
+    notes: HashMap<&'a str, String>,
+    /// Ids of the nodes that are notes, which are only read through the
+    /// references to them.
+    hidden: HashSet<usize>,
+}
+
+/// What an element passes down to its children.
+#[derive(Debug, Clone, Copy, Default)]
+struct Inherited {
+    style: Style,
+    align: Option<Align>,
+    transform: TextTransform,
+    /// Whitespace and line breaks are kept as written.
+    preformatted: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum TextTransform {
+    #[default]
+    None,
+    Uppercase,
+    Lowercase,
+    Capitalize,
+}
+
+impl TextTransform {
+    /// `s` transformed, given the `text` of the block before it.
+    fn apply<'a>(self, s: &'a str, text: &str) -> Cow<'a, str> {
+        match self {
+            Self::None => Cow::Borrowed(s),
+            Self::Uppercase => Cow::Owned(s.to_uppercase()),
+            Self::Lowercase => Cow::Owned(s.to_lowercase()),
+            Self::Capitalize => {
+                let mut word_start = text.is_empty() || text.ends_with(char::is_whitespace);
+                let mut out = String::with_capacity(s.len());
+                for c in s.chars() {
+                    match word_start && c.is_alphabetic() {
+                        true => out.extend(c.to_uppercase()),
+                        false => out.push(c),
+                    }
+                    word_start = c.is_whitespace();
                 }
-                _ => {}
+                Cow::Owned(out)
             }
         }
+    }
+}
 
-        let mut styles = simplecss::StyleSheet::new();
-        for style in raw_stylesheets.iter() {
-            styles.parse_more(style);
-        }
+impl Book for Epub {
+    fn title(&self) -> &str {
+        &self.metadata.title
+    }
 
-        // panic!("{:#?}", styles.rules);
+    fn authors(&self) -> &[Author] {
+        &self.metadata.creators
+    }
 
-        let mut rules = Vec::new();
+    fn identifier(&self) -> &str {
+        &self.metadata.identifier
+    }
 
-        for (i, rule) in styles.rules.iter().enumerate() {
-            for dec in &rule.declarations {
-                match dec.name {
-                    "font-style" if dec.value == "italic" || dec.value.contains("oblique") => {
-                        rules.push((i, CssAttribute::Style(Style::ITALIC)))
-                    }
-                    "font-weight"
-                        if matches!(dec.value, "bold" | "bolder")
-                            || dec.value.parse::<usize>().is_ok_and(|x| x > 400) =>
-                    {
-                        rules.push((i, CssAttribute::Style(Style::BOLD)))
-                    }
-                    "text-align" => {
-                        let align = match dec.value {
-                            "left" => Align::Left,
-                            "center" => Align::Center,
-                            "right" => Align::Right,
-                            "justify" => Align::Left,
-                            "inherit" => continue,
-                            a => panic!("invalid text-align? ({a})"),
-                        };
-                        rules.push((i, CssAttribute::Align(align)))
-                    }
-                    _ => {}
-                }
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn chapters(&self) -> &[TocEntry] {
+        &self.toc.0
+    }
+
+    /// Uncompressed size of each chapter's document.
+    fn chapter_sizes(&mut self) -> Vec<u64> {
+        (0..self.chapter_count())
+            .map(|entry| {
+                let item_idx = self.spine.0[self.toc.0[entry].idx];
+                self.archive.size(item_idx).unwrap_or(0)
+            })
+            .collect()
+    }
+
+    fn traverse(
+        &mut self,
+        entry: usize,
+        options: &TextOptions,
+        cb: &mut dyn FnMut(Content<'_>, Option<Align>),
+    ) -> anyhow::Result<()> {
+        let item_idx = self.spine.0[self.toc.0[entry].idx];
+        let data = self.archive.retrieve(item_idx)?;
+        let data = repair::well_formed(&data);
+        let xml = roxmltree::Document::parse(&data)
+            .with_context(|| format!("failed to parse `{}`", self.toc.0[entry].name))?;
+        let (head, body) = head_and_body(&xml)?;
+
+        let stylesheets = match head {
+            Some(head) => stylesheets(head, |href| {
+                let css_item = self.archive.resolve_hyperlink(item_idx, href)?;
+                self.archive.retrieve_idx(css_item)
+            })?,
+            None => Vec::new(),
+        };
+        let documents = &mut |path: &str| {
+            let target = self.archive.resolve_hyperlink(item_idx, path)?;
+            Ok(match target == item_idx {
+                true => None,
+                false => Some(self.archive.retrieve(target)?),
+            })
+        };
+        traverse_document(body, &stylesheets, options, cb, None, documents)
+    }
+
+    fn image(&mut self, entry: usize, href: &str) -> anyhow::Result<Vec<u8>> {
+        let item_idx = self.spine.0[self.toc.0[entry].idx];
+        let image_idx = self.archive.resolve_hyperlink(item_idx, href)?;
+        self.archive.retrieve_bytes(image_idx)
+    }
+}
+
+/// The head, if there is one, and body of an (X)HTML document.
+pub(crate) fn head_and_body<'a, 'input>(
+    xml: &'a roxmltree::Document<'input>,
+) -> anyhow::Result<(Option<Node<'a, 'input>>, Node<'a, 'input>)> {
+    let root = xml.root_element();
+    let head = root.children().find(|n| n.has_tag_name("head"));
+    let body = root
+        .children()
+        .find(|n| n.has_tag_name("body"))
+        .context("missing body")?;
+    Ok((head, body))
+}
+
+/// Stylesheets linked from or embedded in the head of a document, reading
+/// linked ones with `load`.
+pub(crate) fn stylesheets(
+    head: Node,
+    mut load: impl FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<Vec<String>> {
+    let mut raw_stylesheets = Vec::new();
+    for node in head.children().filter(Node::is_element) {
+        match node.tag_name().name() {
+            "link" if node.attribute("rel") == Some("stylesheet") => {
+                let href = node
+                    .attribute("href")
+                    .context("stylesheet link without href")?;
+                raw_stylesheets.push(load(href)?);
             }
+            "style" if matches!(node.attribute("type"), Some("text/css") | None) => {
+                raw_stylesheets.push(node.text().context("style tag without text")?.to_owned());
+            }
+            _ => {}
         }
+    }
+    Ok(raw_stylesheets)
+}
+
+/// Reads the body of a document, styled by `stylesheets`. With `range`, only
+/// the nodes with ids in it are read, so one document can hold several
+/// chapters. Notes in other documents are read from the source `documents`
+/// gives for a link's path, or `None` if the path is to this document.
+pub(crate) fn traverse_document(
+    body: Node,
+    stylesheets: &[String],
+    options: &TextOptions,
+    mut cb: &mut dyn FnMut(Content<'_>, Option<Align>),
+    range: Option<Range<usize>>,
+    documents: &mut dyn FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> anyhow::Result<()> {
+    let mut styles = simplecss::StyleSheet::new();
+    for style in stylesheets.iter() {
+        styles.parse_more(style);
+    }
 
-        // panic!("{:#?}", body.document().input_text());
-        traverse_body(
-            body,
-            &mut cb,
-            &replacements,
-            &styles,
-            &rules,
-            Style::empty(),
-            None,
-        )?;
+    // panic!("{:#?}", styles.rules);
 
-        Ok((self.title(), self.toc.0[entry].name.as_ref()))
+    let mut rules = Vec::new();
+    for (i, rule) in styles.rules.iter().enumerate() {
+        for dec in &rule.declarations {
+            if let Some(attribute) = CssAttribute::parse(dec) {
+                rules.push(Declared {
+                    rule: i,
+                    important: dec.important,
+                    attribute,
+                });
+            }
+        }
     }
 
-    pub fn title(&self) -> &str {
-        &self.metadata.title
+    let (notes, hidden) = find_notes(body, documents);
+    let reader = &Reader {
+        options,
+        styles: &styles,
+        rules: &rules,
+        notes,
+        hidden,
+    };
+
+    // panic!("{:#?}", body.document().input_text());
+    match range {
+        None => _ = traverse_body(body, &mut cb, reader, Inherited::default())?,
+        Some(range) => traverse_range(body, &mut cb, reader, Inherited::default(), &range)?,
     }
+
+    Ok(())
 }
 
-fn update_style(
-    styles: &StyleSheet,
-    rules: &[(usize, CssAttribute)],
+/// Reads the nodes with ids in `range`. Nodes partly in it are only
+/// descended into, since a chapter can start partway through a container.
+fn traverse_range(
     node: Node,
-    mut style: Style,
-    mut align: Option<Align>,
-) -> (Style, Option<Align>) {
-    // TODO apply style from inline style attribute
-    for added_style in rules.iter().filter_map(|&(i, style)| {
-        styles.rules[i]
-            .selector
-            .matches(&XmlNode(node))
-            .then_some(style)
-    }) {
-        match added_style {
-            CssAttribute::Style(s) => style |= s,
-            CssAttribute::Align(a) => align = Some(a),
+    cb: &mut impl FnMut(Content<'_>, Option<Align>),
+    reader: &Reader,
+    inherited: Inherited,
+    range: &Range<usize>,
+) -> anyhow::Result<()> {
+    if is_unread(node, reader) {
+        return Ok(());
+    }
+    let ids = |node: Node| {
+        let first = node.id().get_usize();
+        let last = node
+            .descendants()
+            .next_back()
+            .map_or(first, |n| n.id().get_usize());
+        (first, last)
+    };
+    let (first, last) = ids(node);
+    if last < range.start || first >= range.end {
+        return Ok(());
+    }
+    if range.contains(&first) && range.contains(&last) {
+        traverse_body(node, cb, reader, inherited)?;
+        return Ok(());
+    }
+    let inherited = update_style(reader.styles, reader.rules, node, inherited);
+    // inline children are only ever wholly in a chapter or out of it
+    let mut run = Vec::new();
+    for child in node.children() {
+        let (first, last) = ids(child);
+        if is_inline(child) && range.contains(&first) && range.contains(&last) {
+            run.push(child);
+            continue;
         }
+        anonymous_block(&run, cb, reader, inherited)?;
+        run.clear();
+        traverse_range(child, cb, reader, inherited, range)?;
     }
+    anonymous_block(&run, cb, reader, inherited)
+}
+
+/// What `node` inherits, updated by its own style. Declarations apply in
+/// order of importance, specificity and where they're written, after the
+/// defaults of the element and before its `style` attribute.
+fn update_style(
+    styles: &StyleSheet,
+    rules: &[Declared],
+    node: Node,
+    mut inherited: Inherited,
+) -> Inherited {
     match node.tag_name().name() {
-        "i" | "em" => style |= Style::ITALIC,
-        "b" | "strong" => style |= Style::BOLD,
-        "center" => align = Some(Align::Center),
+        "i" | "em" | "cite" | "var" | "dfn" => inherited.style |= Style::ITALIC,
+        "b" | "strong" | "th" => inherited.style |= Style::BOLD,
+        "u" | "ins" => inherited.style |= Style::UNDERLINE,
+        "s" | "strike" | "del" => inherited.style |= Style::STRIKETHROUGH,
+        "sup" => inherited.style |= Style::SUPERSCRIPT,
+        "sub" => inherited.style |= Style::SUBSCRIPT,
+        "code" | "tt" | "kbd" | "samp" => inherited.style |= Style::MONOSPACE,
+        "small" => inherited.style |= Style::SMALL,
+        "center" => inherited.align = Some(Align::Center),
+        "pre" => inherited.preformatted = true,
         _ => {}
     }
-    (style, align)
+    if !node.is_element() {
+        return inherited;
+    }
+
+    let element = XmlNode(node);
+    // the `style` attribute outranks every selector
+    let inline = [u8::MAX; 3];
+    let mut declared: Vec<_> = rules
+        .iter()
+        .filter(|d| styles.rules[d.rule].selector.matches(&element))
+        .map(|d| {
+            let specificity = styles.rules[d.rule].selector.specificity();
+            (d.important, specificity, d.attribute)
+        })
+        .chain(
+            node.attribute("style")
+                .into_iter()
+                .flat_map(simplecss::DeclarationTokenizer::from)
+                .filter_map(|dec| Some((dec.important, inline, CssAttribute::parse(&dec)?))),
+        )
+        .collect();
+    // stable, so later declarations still win ties
+    declared.sort_by_key(|&(important, specificity, _)| (important, specificity));
+    for (_, _, attribute) in declared {
+        match attribute {
+            CssAttribute::Style(s, true) => inherited.style |= s,
+            CssAttribute::Style(s, false) => inherited.style &= !s,
+            CssAttribute::Align(a) => inherited.align = Some(a),
+            CssAttribute::Transform(t) => inherited.transform = t,
+        }
+    }
+    inherited
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -753,11 +1055,39 @@ pub enum Align {
     Right,
 }
 
+/// How the text of a chapter is normalised while it's read.
+pub struct TextOptions<'a> {
+    /// Characters paired with what they're replaced by.
+    pub replacements: (&'a [char], &'a [String]),
+    pub quotes: Quotes,
+    pub notes: Notes,
+}
+
 pub enum Content<'a> {
     Header(&'a str, Styling<Len>),
     Paragraph(&'a str, Styling<Len>),
     Quote(&'a str, Styling<Len>),
-    Image,
+    /// What's shown before a list item, such as its bullet or number, and
+    /// the item's text.
+    ListItem(&'a str, &'a str, Styling<Len>),
+    /// Text whose whitespace and line breaks are kept as written.
+    Preformatted(&'a str, Styling<Len>),
+    /// The cells of a table row, and the width of each column of the table.
+    TableRow(Vec<(&'a str, Styling<Len>)>, &'a [usize]),
+    /// A note referred to by the text before it, to be shown but not typed.
+    Note(&'a str),
+    /// Image source as written in the document, and its alt text.
+    Image(&'a str, Option<&'a str>),
+}
+
+fn image_source<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    match node.tag_name().name() {
+        "img" => node.attribute("src"),
+        "image" => node
+            .attribute(("http://www.w3.org/1999/xlink", "href"))
+            .or_else(|| node.attribute("href")),
+        _ => None,
+    }
 }
 
 // traverse should take replacements as argument
@@ -767,114 +1097,377 @@ pub enum Content<'a> {
 fn traverse_body(
     node: roxmltree::Node,
     cb: &mut impl FnMut(Content<'_>, Option<Align>),
-    replacements: &(&[char], &[&str]),
-    styles: &StyleSheet,
-    rules: &[(usize, CssAttribute)],
-    style: Style,
-    align: Option<Align>,
+    reader: &Reader,
+    inherited: Inherited,
 ) -> anyhow::Result<bool> {
-    fn recurse(
-        node: roxmltree::Node,
-        cb: &mut impl FnMut(Content<'_>, Option<Align>),
-        replacements: &(&[char], &[&str]),
-        styles: &StyleSheet,
-        rules: &[(usize, CssAttribute)],
-        style: Style,
-        align: Option<Align>,
-    ) -> anyhow::Result<bool> {
-        for node in node.children() {
-            if traverse_body(node, cb, replacements, styles, rules, style, align)? {
-                return Ok(true);
-            }
-        }
-        Ok(false)
-    }
-
     fn accumulate_text(
         node: roxmltree::Node,
-        replacements: &(&[char], &[&str]),
-        styles: &StyleSheet,
-        rules: &[(usize, CssAttribute)],
-        style: Style,
-        align: Option<Align>,
+        reader: &Reader,
+        inherited: Inherited,
     ) -> anyhow::Result<(String, Styling<Len>)> {
         let mut text = String::new();
         let mut styling = Styling::builder();
-        traverse_block(
-            node,
-            replacements,
-            styles,
-            rules,
-            style,
-            align,
-            &mut text,
-            &mut styling,
-        )?;
-        trim_end_in_place(&mut text);
+        traverse_block(node, reader, inherited, &mut text, &mut styling)?;
+        finish_block(&mut text, reader.options);
         Ok((text, styling.build()))
     }
 
+    if is_unread(node, reader) {
+        return Ok(false);
+    }
     // panic!("{}", node.document().input_text());
-    let (style, align) = update_style(styles, rules, node, style, align);
+    let inherited = update_style(reader.styles, reader.rules, node, inherited);
 
     match node.tag_name().name() {
         "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
-            let (text, styling) = accumulate_text(node, replacements, styles, rules, style, align)?;
+            let (text, styling) = accumulate_text(node, reader, inherited)?;
             if !text.is_empty() {
-                cb(Content::Header(&text, styling), align);
+                cb(Content::Header(&text, styling), inherited.align);
             }
+            after_text(node, cb, reader, inherited.align);
         }
-        "p" => {
-            let (text, styling) = accumulate_text(node, replacements, styles, rules, style, align)?;
+        "p" | "dt" | "caption" => {
+            let (text, styling) = accumulate_text(node, reader, inherited)?;
             if !text.is_empty() {
-                cb(Content::Paragraph(&text, styling), align);
+                cb(Content::Paragraph(&text, styling), inherited.align);
             }
+            after_text(node, cb, reader, inherited.align);
         }
         "blockquote" => {
-            let (text, styling) = accumulate_text(node, replacements, styles, rules, style, align)?;
+            let (text, styling) = accumulate_text(node, reader, inherited)?;
+            if !text.is_empty() {
+                cb(Content::Quote(&text, styling), inherited.align);
+            }
+            after_text(node, cb, reader, inherited.align);
+        }
+        "li" | "dd" => {
+            let mut text = String::new();
+            let mut styling = Styling::builder();
+            // nested lists are items of their own, after this one
+            let (nested, own): (Vec<_>, Vec<_>) = node
+                .children()
+                .partition(|n| matches!(n.tag_name().name(), "ul" | "ol" | "dl"));
+            for &child in &own {
+                traverse_block(child, reader, inherited, &mut text, &mut styling)?;
+            }
+            finish_block(&mut text, reader.options);
             if !text.is_empty() {
-                cb(Content::Quote(&text, styling), align);
+                let marker = list_marker(node);
+                cb(
+                    Content::ListItem(&marker, &text, styling.build()),
+                    inherited.align,
+                );
+            }
+            for &child in &own {
+                after_text(child, cb, reader, inherited.align);
+            }
+            for child in nested {
+                traverse_body(child, cb, reader, inherited)?;
             }
         }
-        n if n == "image" || (n == "img" && node.has_attribute("src")) => {
-            cb(Content::Image, align);
+        "pre" => {
+            let (text, styling) = accumulate_text(node, reader, inherited)?;
+            if !text.is_empty() {
+                cb(Content::Preformatted(&text, styling), inherited.align);
+            }
+            after_text(node, cb, reader, inherited.align);
         }
-        _ => _ = recurse(node, cb, replacements, styles, rules, style, align)?,
+        "table" => {
+            if let Some(caption) = node.children().find(|n| n.has_tag_name("caption")) {
+                traverse_body(caption, cb, reader, inherited)?;
+            }
+            // rows of tables nested in this one are left to those tables
+            let row_nodes: Vec<_> = node
+                .descendants()
+                .filter(|n| {
+                    n.has_tag_name("tr")
+                        && n.ancestors().find(|a| a.has_tag_name("table")) == Some(node)
+                })
+                .collect();
+            let mut rows = Vec::new();
+            for &row in &row_nodes {
+                let inherited = update_style(reader.styles, reader.rules, row, inherited);
+                let mut cells = Vec::new();
+                for cell in row
+                    .children()
+                    .filter(|n| n.has_tag_name("td") || n.has_tag_name("th"))
+                {
+                    cells.push(accumulate_text(cell, reader, inherited)?);
+                }
+                // empty trailing cells would only be padding
+                while cells.last().is_some_and(|(text, _)| text.is_empty()) {
+                    cells.pop();
+                }
+                if !cells.is_empty() {
+                    rows.push(cells);
+                }
+            }
+            let mut widths = Vec::new();
+            for row in &rows {
+                widths.resize(widths.len().max(row.len()), 0);
+                for (width, (text, _)) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(text.chars().count());
+                }
+            }
+            for row in rows {
+                let (texts, stylings): (Vec<_>, Vec<_>) = row.into_iter().unzip();
+                let cells = texts.iter().map(String::as_str).zip(stylings).collect();
+                cb(Content::TableRow(cells, &widths), inherited.align);
+            }
+            // the caption's images and notes came with it
+            for row in row_nodes {
+                after_text(row, cb, reader, inherited.align);
+            }
+        }
+        _ => match image_source(node) {
+            Some(src) => cb(Content::Image(src, node.attribute("alt")), inherited.align),
+            None => traverse_children(node, cb, reader, inherited)?,
+        },
     }
     Ok(false)
 }
 
-fn traverse_block(
+/// What's shown before a list item: its bullet, number or indentation,
+/// indented further for each list it's nested in.
+fn list_marker(node: Node) -> String {
+    let depth = node
+        .ancestors()
+        .filter(|n| matches!(n.tag_name().name(), "ul" | "ol" | "dl"))
+        .count()
+        .saturating_sub(1);
+    let indent = "  ".repeat(depth);
+    let parent = node.parent_element();
+    match parent.map(|p| p.tag_name().name()) {
+        _ if node.has_tag_name("dd") => format!("{indent}    "),
+        Some("ol") => {
+            let start = parent
+                .and_then(|p| p.attribute("start"))
+                .and_then(|s| s.trim().parse::<i64>().ok())
+                .unwrap_or(1);
+            let index = node
+                .prev_siblings()
+                .skip(1)
+                .filter(|n| n.has_tag_name("li"))
+                .count();
+            format!("{indent}{}. ", start + index as i64)
+        }
+        _ => format!("{indent}• "),
+    }
+}
+
+/// Elements that are blocks of their own, rather than part of the text
+/// around them.
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Elements inside a body with no text to read, such as the scripts of saved
+/// web pages.
+pub(crate) const UNREAD: &[&str] = &["noscript", "script", "style", "template"];
+
+/// Whether `node` is left out of the text, being a note or unreadable.
+fn is_unread(node: Node, reader: &Reader) -> bool {
+    reader.hidden.contains(&node.id().get_usize()) || UNREAD.contains(&node.tag_name().name())
+}
+
+/// Whether `node` is text, or an element with no blocks in it, and so part
+/// of an anonymous block with its inline neighbours.
+pub(crate) fn is_inline(node: Node) -> bool {
+    !node.is_element()
+        || node
+            .descendants()
+            .all(|n| !BLOCKS.contains(&n.tag_name().name()))
+}
+
+/// Reads the children of a container. Runs of text and inline elements
+/// between its blocks, as converted books often have, are read as
+/// paragraphs of their own.
+fn traverse_children(
+    node: Node,
+    cb: &mut impl FnMut(Content<'_>, Option<Align>),
+    reader: &Reader,
+    inherited: Inherited,
+) -> anyhow::Result<()> {
+    let mut run = Vec::new();
+    for child in node.children() {
+        if is_inline(child) {
+            run.push(child);
+            continue;
+        }
+        anonymous_block(&run, cb, reader, inherited)?;
+        run.clear();
+        traverse_body(child, cb, reader, inherited)?;
+    }
+    anonymous_block(&run, cb, reader, inherited)
+}
+
+/// Reads a run of inline nodes as a paragraph, if there's any text in them.
+fn anonymous_block(
+    nodes: &[Node],
+    cb: &mut impl FnMut(Content<'_>, Option<Align>),
+    reader: &Reader,
+    inherited: Inherited,
+) -> anyhow::Result<()> {
+    let mut text = String::new();
+    let mut styling = Styling::builder();
+    for &node in nodes {
+        traverse_block(node, reader, inherited, &mut text, &mut styling)?;
+    }
+    finish_block(&mut text, reader.options);
+    if !text.is_empty() {
+        cb(Content::Paragraph(&text, styling.build()), inherited.align);
+    }
+    for &node in nodes {
+        after_text(node, cb, reader, inherited.align);
+    }
+    Ok(())
+}
+
+// images and notes inside text blocks aren't part of the text, so they're
+// placed after it
+fn after_text(
     node: roxmltree::Node,
-    replacements: &(&[char], &[&str]),
-    styles: &StyleSheet,
-    rules: &[(usize, CssAttribute)],
-    style: Style,
+    cb: &mut impl FnMut(Content<'_>, Option<Align>),
+    reader: &Reader,
     align: Option<Align>,
+) {
+    for node in node.descendants() {
+        if let Some(src) = image_source(node) {
+            cb(Content::Image(src, node.attribute("alt")), align);
+        }
+        if reader.options.notes == Notes::Panel && notes::is_reference(node) {
+            if let Some(note) = node
+                .attribute("href")
+                .and_then(|href| reader.notes.get(href))
+            {
+                cb(Content::Note(note), align);
+            }
+        }
+    }
+}
+
+/// Text of the notes that references in `body` link to, by the links'
+/// `href`, and the ids of the nodes in `body` that are notes.
+fn find_notes<'a>(
+    body: Node<'a, '_>,
+    documents: &mut dyn FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> (HashMap<&'a str, String>, HashSet<usize>) {
+    let mut hidden: HashSet<_> = body
+        .descendants()
+        .filter(|&n| notes::is_note(n))
+        .map(|n| n.id().get_usize())
+        .collect();
+    let references: Vec<_> = body
+        .descendants()
+        .filter(|&n| notes::is_reference(n))
+        .filter_map(|n| {
+            let href = n.attribute("href")?;
+            let (path, id) = href.split_once('#')?;
+            Some((n, href, path, id))
+        })
+        .collect();
+    let mut paths: Vec<_> = references.iter().map(|&(_, _, path, _)| path).collect();
+    paths.sort_unstable();
+    paths.dedup();
+
+    let mut texts = HashMap::new();
+    for path in paths {
+        let source = match path.is_empty() {
+            true => None,
+            // a broken link only loses its note
+            false => match documents(path) {
+                Ok(source) => source.map(|s| repair::well_formed(&s).into_owned()),
+                Err(_) => continue,
+            },
+        };
+        let other = match source.as_deref().map(roxmltree::Document::parse) {
+            Some(Ok(xml)) => Some(xml),
+            Some(Err(_)) => continue,
+            None => None,
+        };
+        // in document order, so that links back from notes in this document
+        // are already hidden and aren't taken for references
+        for &(reference, href, _, id) in references.iter().filter(|r| r.2 == path) {
+            if reference
+                .ancestors()
+                .any(|n| hidden.contains(&n.id().get_usize()))
+            {
+                continue;
+            }
+            let target = match &other {
+                Some(xml) => xml.descendants().find(|n| n.attribute("id") == Some(id)),
+                None => body
+                    .document()
+                    .descendants()
+                    .find(|n| n.attribute("id") == Some(id)),
+            };
+            let Some(target) = target else {
+                continue;
+            };
+            let note = notes::note_of(target);
+            // a target in the referring text isn't a note
+            if other.is_none() && note.descendants().any(|n| n == reference) {
+                continue;
+            }
+            if other.is_none() {
+                hidden.insert(note.id().get_usize());
+            }
+            texts.insert(href, notes::text(note, reference.attribute("id")));
+        }
+    }
+    (texts, hidden)
+}
+
+fn traverse_block(
+    node: roxmltree::Node,
+    reader: &Reader,
+    inherited: Inherited,
     text: &mut String,
     styling: &mut crate::style::Builder<Len>,
 ) -> anyhow::Result<bool> {
     fn recurse(
         node: roxmltree::Node,
-        replacements: &(&[char], &[&str]),
-        styles: &StyleSheet,
-        rules: &[(usize, CssAttribute)],
-        style: Style,
-        align: Option<Align>,
+        reader: &Reader,
+        inherited: Inherited,
         text: &mut String,
         styling: &mut crate::style::Builder<Len>,
     ) -> anyhow::Result<bool> {
         for node in node.children() {
-            if traverse_block(
-                node,
-                replacements,
-                styles,
-                rules,
-                style,
-                align,
-                text,
-                styling,
-            )? {
+            if traverse_block(node, reader, inherited, text, styling)? {
                 return Ok(true);
             }
         }
@@ -883,64 +1476,131 @@ fn traverse_block(
 
     if node.is_text() {
         let s = node.text().context("invalid text node")?;
+        let s = inherited.transform.apply(s, text);
+        match inherited.preformatted {
+            true => push_preformatted(text, styling, &s, inherited.style, reader.options),
+            false => push_text(text, styling, &s, inherited.style, reader.options),
+        }
+        return Ok(false);
+    }
 
-        if !s.is_empty() {
-            let start = Len::new(text.len(), text.chars().count());
+    if is_unread(node, reader) {
+        return Ok(false);
+    }
+    if notes::is_reference(node) {
+        let note = node
+            .attribute("href")
+            .and_then(|href| reader.notes.get(href));
+        if let (Notes::Inline, Some(note)) = (reader.options.notes, note) {
+            let style = inherited.style.difference(Style::SUPERSCRIPT);
+            push_text(text, styling, " [", style, reader.options);
+            push_text(text, styling, note, style, reader.options);
+            push_text(text, styling, "]", style, reader.options);
+        }
+        return Ok(false);
+    }
 
-            if s.chars().next().is_some_and(|c| c.is_ascii_whitespace())
-                && text.chars().last().is_some()
-                && !text.chars().last().unwrap().is_ascii_whitespace()
-            {
-                text.push(' ');
-            }
+    let inherited = update_style(reader.styles, reader.rules, node, inherited);
 
-            for s in s.split_ascii_whitespace() {
-                let mut last_end = 0;
-                for (start, part) in s.match_indices(replacements.0) {
-                    let part = part.chars().next().unwrap();
-                    let rep_idx = replacements.0.iter().position(|&c| c == part).unwrap();
-                    let to = replacements.1[rep_idx];
-                    let chunk = &s[last_end..start];
-                    text.push_str(chunk);
-                    text.push_str(to);
-                    last_end = start + part.len_utf8();
-                }
-                text.push_str(&s[last_end..s.len()]);
-                text.push(' ');
-            }
+    if node.tag_name().name() == "br" {
+        text.push('\n');
+    }
 
-            if text.len() > start.bytes
-                && s.chars().last().is_some_and(|c| !c.is_ascii_whitespace())
-            {
-                text.pop();
-            }
+    recurse(node, reader, inherited, text, styling)
+}
 
-            let end = Len::new(
-                text.len(),
-                start.chars + text[start.bytes..].chars().count(),
-            );
+/// Appends a run of text in one style to a block, collapsing whitespace and
+/// making the replacements in `options`.
+pub(crate) fn push_text(
+    text: &mut String,
+    styling: &mut crate::style::Builder<Len>,
+    s: &str,
+    style: Style,
+    options: &TextOptions,
+) {
+    if s.is_empty() {
+        return;
+    }
+    let start = Len::new(text.len(), text.chars().count());
 
-            styling.add(style, start..end);
-        }
-        return Ok(false);
+    if s.chars().next().is_some_and(|c| c.is_ascii_whitespace())
+        && text.chars().last().is_some()
+        && !text.chars().last().unwrap().is_ascii_whitespace()
+    {
+        text.push(' ');
     }
 
-    let (style, align) = update_style(styles, rules, node, style, align);
+    for s in s.split_ascii_whitespace() {
+        push_replaced(text, s, options);
+        text.push(' ');
+    }
 
-    if node.tag_name().name() == "br" {
-        text.push('\n');
+    if text.len() > start.bytes && s.chars().last().is_some_and(|c| !c.is_ascii_whitespace()) {
+        text.pop();
+    }
+
+    let end = Len::new(
+        text.len(),
+        start.chars + text[start.bytes..].chars().count(),
+    );
+
+    styling.add(style, start..end);
+}
+
+/// Appends a run of preformatted text in one style to a block, keeping its
+/// whitespace but expanding tabs, and making the replacements in `options`.
+pub(crate) fn push_preformatted(
+    text: &mut String,
+    styling: &mut crate::style::Builder<Len>,
+    s: &str,
+    style: Style,
+    options: &TextOptions,
+) {
+    // the line break that starts a `pre` isn't part of its text
+    let s = match text.is_empty() {
+        true => s.strip_prefix("\r\n").or(s.strip_prefix('\n')).unwrap_or(s),
+        false => s,
+    };
+    if s.is_empty() {
+        return;
     }
+    let start = Len::new(text.len(), text.chars().count());
+    let s = s.replace("\r\n", "\n").replace('\t', "    ");
+    push_replaced(text, &s, options);
+    let end = Len::new(
+        text.len(),
+        start.chars + text[start.bytes..].chars().count(),
+    );
+    styling.add(style, start..end);
+}
 
-    recurse(
-        node,
-        replacements,
-        styles,
-        rules,
-        style,
-        align,
-        text,
-        styling,
-    )
+/// Appends `s` with the replacements in `options` made.
+fn push_replaced(text: &mut String, s: &str, options: &TextOptions) {
+    let mut last_end = 0;
+    for (start, part) in s.match_indices(options.replacements.0) {
+        let part = part.chars().next().unwrap();
+        let rep_idx = options
+            .replacements
+            .0
+            .iter()
+            .position(|&c| c == part)
+            .unwrap();
+        let to = &options.replacements.1[rep_idx];
+        text.push_str(&s[last_end..start]);
+        text.push_str(to);
+        last_end = start + part.len_utf8();
+    }
+    text.push_str(&s[last_end..]);
+}
+
+/// Tidies up the text of a block once all of it is collected.
+pub(crate) fn finish_block(text: &mut String, options: &TextOptions) {
+    trim_end_in_place(text);
+    // quotations can span several text nodes, so this has to wait until the
+    // whole block is collected
+    if options.quotes == Quotes::Swap {
+        quotes::swap(text);
+    }
 }
 
 fn trim_end_in_place(s: &mut String) -> usize {
@@ -959,7 +1619,7 @@ fn parse_hyperlink(base: &str, href: &str) -> anyhow::Result<Url> {
 // TODO save previews so can incremental search
 // TODO iterator for multiple results
 pub trait SearchBackend {
-    fn search(&self, title: &str) -> anyhow::Result<Option<Epub>>;
+    fn search(&self, title: &str) -> anyhow::Result<Option<Box<dyn Book>>>;
 }
 
 pub struct Directory {
@@ -967,22 +1627,25 @@ pub struct Directory {
 }
 
 impl SearchBackend for Directory {
-    fn search(&self, title: &str) -> anyhow::Result<Option<Epub>> {
-        let parse = |entry: fs::DirEntry| -> anyhow::Result<Option<Epub>> {
-            match entry
-                .path()
-                .extension()
-                .map(std::ffi::OsStr::to_string_lossy)
-                .as_deref()
-            {
-                Some("epub") => {}
-                _ => anyhow::bail!("not an epub: `{}`", entry.path().to_string_lossy()),
-            }
-            let doc = EpubPreview::from_file(entry.path())?;
-            match doc.title().to_lowercase().contains(&title.to_lowercase()) {
-                true => doc.full().map(Option::Some),
-                false => Ok(None),
+    fn search(&self, title: &str) -> anyhow::Result<Option<Box<dyn Book>>> {
+        let matches = |t: &str| t.to_lowercase().contains(&title.to_lowercase());
+        let parse = |entry: fs::DirEntry| -> anyhow::Result<Option<Box<dyn Book>>> {
+            let path = entry.path();
+            anyhow::ensure!(
+                book::is_book(&path),
+                "not a book: `{}`",
+                path.to_string_lossy()
+            );
+            if path.extension().is_some_and(|ext| ext == "epub") {
+                // reading the title alone is much quicker than the whole book
+                let doc = EpubPreview::from_file(&path)?;
+                return match matches(doc.title()) {
+                    true => Ok(Some(Box::new(doc.full()?))),
+                    false => Ok(None),
+                };
             }
+            let book = book::open(&path)?;
+            Ok(matches(book.title()).then_some(book))
         };
         for entry in fs::read_dir(&self.dir)? {
             let entry = entry?;
@@ -1007,6 +1670,19 @@ impl Directory {
             dir: ebook_directory()?,
         })
     }
+
+    /// Paths of the books in the directory.
+    pub fn books(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut books = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if book::is_book(&path) {
+                books.push(path);
+            }
+        }
+        books.sort();
+        Ok(books)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1017,7 +1693,7 @@ pub struct Author {
 }
 
 impl Author {
-    fn parse(raw: &str) -> Option<Self> {
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
         let mut raw = raw.trim();
         if raw.is_empty() || raw == "Unknown" {
             return None;
@@ -1097,3 +1773,160 @@ impl std::fmt::Display for Author {
         f.write_str(&self.surname)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// Options for reading a document as typed, with its notes read as
+    /// `notes`.
+    pub(crate) fn options(notes: Notes) -> TextOptions<'static> {
+        TextOptions {
+            replacements: (&[], &[]),
+            quotes: Quotes::Keep,
+            notes,
+        }
+    }
+
+    /// Text of each block emitted by `traverse` as it's typed, with the
+    /// length in characters of each run of it in one style. List markers and
+    /// table rows aren't styled, and images and notes are left out.
+    pub(crate) fn read_blocks(
+        traverse: impl FnOnce(&mut dyn FnMut(Content<'_>, Option<Align>)) -> anyhow::Result<()>,
+    ) -> Vec<(String, Vec<(Style, usize)>)> {
+        let runs = |s: &str, styling: &Styling<Len>| {
+            let end = Len::new(s.len(), s.chars().count());
+            styling
+                .iter(Len::default(), end)
+                .map(|(style, len)| (style, len.chars))
+                .collect::<Vec<_>>()
+        };
+        let mut blocks = Vec::new();
+        traverse(&mut |content, _| {
+            blocks.push(match content {
+                Content::Header(s, styling)
+                | Content::Paragraph(s, styling)
+                | Content::Quote(s, styling)
+                | Content::Preformatted(s, styling) => (s.to_owned(), runs(s, &styling)),
+                Content::ListItem(marker, s, styling) => {
+                    (format!("{marker}{s}"), runs(s, &styling))
+                }
+                Content::TableRow(cells, widths) => {
+                    let cells: Vec<_> = cells
+                        .iter()
+                        .zip(widths)
+                        .map(|((s, _), width)| format!("{s:width$}"))
+                        .collect();
+                    (cells.join(" ").trim_end().to_owned(), Vec::new())
+                }
+                Content::Image(..) | Content::Note(..) => return,
+            });
+        })
+        .unwrap();
+        blocks
+    }
+
+    /// Blocks of an XHTML document, where links to other documents lead to
+    /// `other`.
+    fn read_document(
+        xml: &str,
+        css: &[String],
+        notes: Notes,
+        other: Option<&str>,
+    ) -> Vec<(String, Vec<(Style, usize)>)> {
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let (_, body) = head_and_body(&doc).unwrap();
+        let options = options(notes);
+        read_blocks(|cb| {
+            traverse_document(body, css, &options, cb, None, &mut |_| {
+                Ok(other.map(ToOwned::to_owned))
+            })
+        })
+    }
+
+    /// Text of the blocks of an XHTML document without notes.
+    fn read_text(xml: &str) -> Vec<String> {
+        read_document(xml, &[], Notes::Skip, None)
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect()
+    }
+
+    #[test]
+    fn it_cascades_styles() {
+        let xml = r#"<html><head></head><body>
+<p class="a" style="font-weight: normal">x<em>y</em><span style="text-transform: uppercase; text-decoration: underline">z</span></p>
+</body></html>"#;
+        let css = String::from(
+            "p { font-weight: bold } .a { font-style: italic } p { font-style: normal }",
+        );
+        assert_eq!(
+            read_document(xml, &[css], Notes::Skip, None),
+            [(
+                String::from("xyZ"),
+                vec![(Style::ITALIC, 2), (Style::ITALIC | Style::UNDERLINE, 1)]
+            )]
+        );
+    }
+
+    #[test]
+    fn it_reads_loose_text_as_paragraphs() {
+        let xml = "<html><head></head><body>
+Loose <i>text</i>.<div>In a <span>div</span><p>A paragraph.</p>after it</div>
+</body></html>";
+        let blocks = read_document(xml, &[], Notes::Skip, None);
+        let texts: Vec<_> = blocks.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["Loose text.", "In a div", "A paragraph.", "after it"]
+        );
+        assert_eq!(
+            blocks[0].1,
+            [(Style::empty(), 6), (Style::ITALIC, 4), (Style::empty(), 1)]
+        );
+    }
+
+    #[test]
+    fn it_leaves_out_scripts_and_styles() {
+        let xml = "<html><head></head><body>
+<script>var x = 1;</script><style>p { color: red }</style>Loose
+<div><noscript>Enable scripts.</noscript><p>Text.</p><template><p>Later.</p></template></div>
+</body></html>";
+        assert_eq!(read_text(xml), ["Loose", "Text."]);
+    }
+
+    #[test]
+    fn it_types_notes_inline() {
+        let xml = r##"<html xmlns:epub="http://www.idpf.org/2007/ops"><head></head><body>
+<p>Text<sup><a id="r1" href="#n1">1</a></sup> and more<a epub:type="noteref" href="notes.xhtml#n2">2</a>.</p>
+<p id="n1"><a href="#r1">1</a> A note.</p>
+</body></html>"##;
+        let other = r#"<html><body><aside id="n2">Elsewhere.</aside></body></html>"#;
+        let blocks = read_document(xml, &[], Notes::Inline, Some(other));
+        let texts: Vec<_> = blocks.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(texts, ["Text [A note.] and more [Elsewhere.]."]);
+    }
+
+    #[test]
+    fn it_reads_lists_tables_and_preformatted_text() {
+        let xml = "<html><head></head><body>
+<ol start=\"3\"><li>Three</li><li>Four<ul><li>Nested</li></ul></li></ol>
+<pre>
+fn main() {
+\tbody();
+}</pre>
+<table><tr><th>Name</th><th>Age</th></tr><tr><td>Bo</td><td>7</td></tr></table>
+</body></html>";
+        assert_eq!(
+            read_text(xml),
+            [
+                "3. Three",
+                "4. Four",
+                "  • Nested",
+                "fn main() {\n    body();\n}",
+                "Name Age",
+                "Bo   7",
+            ]
+        );
+    }
+}
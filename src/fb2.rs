@@ -0,0 +1,254 @@
+use std::{fs, path::Path};
+
+use anyhow::Context as _;
+use base64::Engine as _;
+use roxmltree::Node;
+
+use crate::{
+    book::{content_identifier, title_from_path, Block, Parsed},
+    epub::Author,
+    style::Style,
+};
+
+type Chapter = (String, usize, Vec<Block>);
+
+/// Reads a FictionBook 2 book. Each section of the body is a chapter, nested
+/// as deep as the sections are.
+pub(crate) fn load(path: &Path) -> anyhow::Result<Parsed> {
+    let data = fs::read(path)?;
+    let text = String::from_utf8_lossy(&data);
+    let xml = roxmltree::Document::parse(&text)
+        .with_context(|| format!("failed to parse `{}`", path.display()))?;
+    let root = xml.root_element();
+
+    let title_info = find(root, &["description", "title-info"]);
+    let title = title_info
+        .and_then(|info| child(info, "book-title"))
+        .map(|node| text_of(node).trim().to_owned())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| title_from_path(path));
+    let authors = title_info
+        .into_iter()
+        .flat_map(|info| info.children().filter(|n| n.has_tag_name("author")))
+        .filter_map(author)
+        .collect();
+    let identifier = find(root, &["description", "document-info", "id"])
+        .map(|node| text_of(node).trim().to_owned())
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| content_identifier("fb2", &data));
+
+    let mut chapters = Vec::new();
+    // the notes body holds footnotes, which aren't read in place
+    for body in root
+        .children()
+        .filter(|n| n.has_tag_name("body") && n.attribute("name") != Some("notes"))
+    {
+        section(body, 0, &mut chapters);
+    }
+
+    let mut book = Parsed::new(path, identifier, title, authors, chapters);
+    for binary in root.children().filter(|n| n.has_tag_name("binary")) {
+        let Some(id) = binary.attribute("id") else {
+            continue;
+        };
+        let encoded: String = text_of(binary).split_whitespace().collect();
+        if let Ok(data) = base64::engine::general_purpose::STANDARD.decode(encoded) {
+            book.images.insert(format!("#{id}"), data);
+        }
+    }
+    Ok(book)
+}
+
+/// Adds the chapter of a section, followed by those of its subsections.
+fn section(node: Node, depth: usize, chapters: &mut Vec<Chapter>) {
+    let mut blocks = Vec::new();
+    let mut subsections = Vec::new();
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "section" => subsections.push(child),
+            _ => block(child, &mut blocks),
+        }
+    }
+    let title = child(node, "title")
+        .map(|title| collapse(&text_of(title)))
+        .filter(|title| !title.is_empty());
+    // a body's own content is only a chapter if there's something to type
+    let is_body = node.has_tag_name("body");
+    if !(is_body && blocks.iter().all(|b| matches!(b, Block::Header(_)))) {
+        let name = title.unwrap_or_else(|| match is_body {
+            true => String::from("Beginning"),
+            false => format!("Section {}", chapters.len() + 1),
+        });
+        chapters.push((name, depth, blocks));
+    }
+    let depth = depth + usize::from(!is_body);
+    for subsection in subsections {
+        section(subsection, depth, chapters);
+    }
+}
+
+fn block(node: Node, blocks: &mut Vec<Block>) {
+    let mut images = Vec::new();
+    match node.tag_name().name() {
+        "title" => {
+            let mut runs = Vec::new();
+            for p in node.children().filter(|n| n.has_tag_name("p")) {
+                if !runs.is_empty() {
+                    runs.push((String::from(" "), Style::empty()));
+                }
+                inline(p, Style::empty(), &mut runs, &mut images);
+            }
+            blocks.push(Block::Header(runs));
+        }
+        "subtitle" => {
+            let mut runs = Vec::new();
+            inline(node, Style::empty(), &mut runs, &mut images);
+            blocks.push(Block::Header(runs));
+        }
+        "p" | "v" | "text-author" => {
+            let mut runs = Vec::new();
+            inline(node, Style::empty(), &mut runs, &mut images);
+            blocks.push(Block::Paragraph(runs));
+        }
+        "epigraph" | "cite" => {
+            let mut inner = Vec::new();
+            for child in node.children().filter(Node::is_element) {
+                block(child, &mut inner);
+            }
+            blocks.extend(inner.into_iter().map(|b| match b {
+                Block::Paragraph(runs) => Block::Quote(runs),
+                b => b,
+            }));
+        }
+        "poem" | "stanza" | "annotation" => {
+            for child in node.children().filter(Node::is_element) {
+                block(child, blocks);
+            }
+        }
+        "table" => {
+            for row in node.children().filter(|n| n.has_tag_name("tr")) {
+                let mut runs = Vec::new();
+                for cell in row.children().filter(Node::is_element) {
+                    if !runs.is_empty() {
+                        runs.push((String::from(" "), Style::empty()));
+                    }
+                    inline(cell, Style::empty(), &mut runs, &mut images);
+                }
+                blocks.push(Block::Paragraph(runs));
+            }
+        }
+        "image" => images.extend(image(node)),
+        _ => {}
+    }
+    blocks.extend(images);
+}
+
+/// Styled runs of the text in `node`, collecting any inline images.
+fn inline(node: Node, style: Style, runs: &mut Vec<(String, Style)>, images: &mut Vec<Block>) {
+    for child in node.children() {
+        if child.is_text() {
+            let text = child.text().unwrap_or_default();
+            match runs.last_mut() {
+                Some((s, last)) if *last == style => s.push_str(text),
+                _ => runs.push((text.to_owned(), style)),
+            }
+            continue;
+        }
+        let style = match child.tag_name().name() {
+            "emphasis" => style | Style::ITALIC,
+            "strong" => style | Style::BOLD,
+            "strikethrough" => style | Style::STRIKETHROUGH,
+            "sup" => style | Style::SUPERSCRIPT,
+            "sub" => style | Style::SUBSCRIPT,
+            "code" => style | Style::MONOSPACE,
+            "image" => {
+                images.extend(image(child));
+                continue;
+            }
+            _ => style,
+        };
+        inline(child, style, runs, images);
+    }
+}
+
+fn image(node: Node) -> Option<Block> {
+    // the href is in the xlink namespace, under whatever prefix the book uses
+    let href = node.attributes().find(|a| a.name() == "href")?.value();
+    let alt = node.attribute("alt").map(str::to_owned);
+    Some(Block::Image(href.to_owned(), alt))
+}
+
+fn author(node: Node) -> Option<Author> {
+    let name: Vec<_> = ["first-name", "middle-name", "last-name"]
+        .into_iter()
+        .filter_map(|part| child(node, part))
+        .map(|part| collapse(&text_of(part)))
+        .filter(|part| !part.is_empty())
+        .collect();
+    match name.is_empty() {
+        true => child(node, "nickname").and_then(|nick| Author::parse(&text_of(nick))),
+        false => Author::parse(&name.join(" ")),
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn find<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| child(node, name))
+}
+
+fn text_of(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_reads_nested_sections() {
+        let xml = r##"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:l="http://www.w3.org/1999/xlink">
+  <body>
+    <title><p>The Book</p></title>
+    <section>
+      <title><p>Part One</p></title>
+      <epigraph><p>Quoted.</p></epigraph>
+      <section>
+        <title><p>Chapter 1</p></title>
+        <p>Some <emphasis>very</emphasis> <strong>bold</strong> text.</p>
+        <image l:href="#pic"/>
+      </section>
+    </section>
+  </body>
+  <body name="notes"><section><p>A note.</p></section></body>
+</FictionBook>"##;
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let body = doc.root_element().first_element_child().unwrap();
+        let mut chapters = Vec::new();
+        section(body, 0, &mut chapters);
+        let names: Vec<_> = chapters.iter().map(|c| (c.0.as_str(), c.1)).collect();
+        assert_eq!(names, [("Part One", 0), ("Chapter 1", 1)]);
+        assert!(matches!(chapters[0].2[1], Block::Quote(_)));
+        assert_eq!(
+            chapters[1].2[1],
+            Block::Paragraph(vec![
+                (String::from("Some "), Style::empty()),
+                (String::from("very"), Style::ITALIC),
+                (String::from(" "), Style::empty()),
+                (String::from("bold"), Style::BOLD),
+                (String::from(" text."), Style::empty()),
+            ])
+        );
+        assert_eq!(chapters[1].2[2], Block::Image(String::from("#pic"), None));
+    }
+}
@@ -0,0 +1,201 @@
+use std::{fmt::Write as _, fs, io, ops::Range, path::PathBuf, str::FromStr, time::Duration};
+
+use crate::{
+    backend::{Keystroke, Len},
+    epub::state_directory,
+    score::WORD_LEN,
+    state::file_name,
+};
+
+/// What a ghost cursor keeps pace with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pace {
+    /// The fastest complete run through the chapter so far.
+    Best,
+    /// A fixed speed in words per minute.
+    Wpm(u32),
+}
+
+impl FromStr for Pace {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "best" => Ok(Self::Best),
+            _ => match s.parse() {
+                Ok(wpm) if wpm > 0 => Ok(Self::Wpm(wpm)),
+                _ => anyhow::bail!("invalid pace `{s}` (expected best or a speed in wpm)"),
+            },
+        }
+    }
+}
+
+/// A second cursor typing the chapter alongside the user.
+#[derive(Debug, Clone)]
+pub enum Ghost {
+    Run(Run),
+    /// Characters per second.
+    Speed(f64),
+}
+
+impl Ghost {
+    pub fn from_wpm(wpm: u32) -> Self {
+        Self::Speed(wpm as f64 * WORD_LEN / 60.0)
+    }
+
+    /// Characters typed by the ghost `elapsed` after the first keystroke,
+    /// when typing started at character `start`. `None` if a run doesn't
+    /// reach `start`.
+    pub fn position(&self, start: usize, elapsed: Duration) -> Option<usize> {
+        match self {
+            Self::Run(run) => {
+                let offset = *run.times.get(start)?;
+                Some(run.times.partition_point(|&t| t <= offset + elapsed))
+            }
+            Self::Speed(cps) => Some(start + 1 + (elapsed.as_secs_f64() * cps) as usize),
+        }
+    }
+}
+
+/// Timing of a complete run through a chapter, as the time since the first
+/// keystroke that each character started being typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    times: Vec<Duration>,
+}
+
+impl Run {
+    /// The run through a chapter of `len` characters, of which the `skipped`
+    /// ranges aren't typed. `None` if typing didn't go from the beginning of
+    /// the chapter to its end.
+    pub fn from_keystrokes(
+        keystrokes: &[Keystroke],
+        skipped: &[Range<Len>],
+        len: usize,
+    ) -> Option<Self> {
+        let mut times = Vec::new();
+        let mut skipped = skipped.iter().peekable();
+        // skipped text is reached as soon as the character before it is typed
+        let mut skip = |times: &mut Vec<Duration>| {
+            while let Some(range) = skipped.next_if(|r| r.start.chars <= times.len()) {
+                let time = times.last().copied().unwrap_or_default();
+                times.resize(range.end.chars.max(times.len()), time);
+            }
+        };
+        skip(&mut times);
+        for k in keystrokes {
+            if k.position.chars == times.len() {
+                times.push(k.time);
+                skip(&mut times);
+            }
+        }
+        (times.len() == len).then_some(Self { times })
+    }
+
+    /// Number of characters the run goes through.
+    pub fn chars(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.times.last().copied().unwrap_or_default()
+    }
+
+    pub fn load(identifier: &str, chapter: usize) -> anyhow::Result<Option<Self>> {
+        let data = match fs::read_to_string(Self::path(identifier, chapter)?) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut time = Duration::ZERO;
+        let mut times = Vec::new();
+        for delta in data.split_ascii_whitespace() {
+            time += Duration::from_millis(delta.parse()?);
+            times.push(time);
+        }
+        Ok(Some(Self { times }))
+    }
+
+    pub fn save(&self, identifier: &str, chapter: usize) -> anyhow::Result<()> {
+        let path = Self::path(identifier, chapter)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut data = String::new();
+        let mut last = 0;
+        for time in &self.times {
+            let millis = time.as_millis();
+            writeln!(data, "{}", millis - last).unwrap();
+            last = millis;
+        }
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    fn path(identifier: &str, chapter: usize) -> anyhow::Result<PathBuf> {
+        let mut path = state_directory()?;
+        path.push("runs");
+        path.push(file_name(identifier));
+        path.push(chapter.to_string());
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keystroke(chars: usize, millis: u64) -> Keystroke {
+        Keystroke {
+            time: Duration::from_millis(millis),
+            position: Len::new(chars, chars),
+            expected: 'a',
+            typed: 'a',
+            correct: true,
+        }
+    }
+
+    #[test]
+    fn it_follows_a_run() {
+        let keystrokes = [
+            keystroke(0, 0),
+            keystroke(1, 100),
+            keystroke(2, 200),
+            keystroke(1, 300),
+            keystroke(1, 400),
+            keystroke(2, 500),
+        ];
+        let ghost = Ghost::Run(Run::from_keystrokes(&keystrokes, &[], 3).unwrap());
+        let at = |start, millis| ghost.position(start, Duration::from_millis(millis));
+        assert_eq!(at(0, 0), Some(1));
+        assert_eq!(at(0, 150), Some(2));
+        assert_eq!(at(0, 1000), Some(3));
+        assert_eq!(at(1, 0), Some(2));
+        assert_eq!(at(5, 0), None);
+    }
+
+    #[test]
+    fn it_fills_in_skipped_text() {
+        let len = |chars| Len::new(chars, chars);
+        let skipped = [len(0)..len(2), len(4)..len(6)];
+        let keystrokes = [
+            keystroke(2, 0),
+            keystroke(3, 100),
+            keystroke(6, 200),
+            keystroke(7, 300),
+        ];
+        let run = Run::from_keystrokes(&keystrokes, &skipped, 8).unwrap();
+        assert_eq!(run.chars(), 8);
+        assert_eq!(run.elapsed(), Duration::from_millis(300));
+        let ghost = Ghost::Run(run);
+        let at = |start, millis| ghost.position(start, Duration::from_millis(millis));
+        assert_eq!(at(2, 0), Some(3));
+        assert_eq!(at(2, 100), Some(6));
+        assert_eq!(at(2, 200), Some(7));
+
+        // a run that stops before the end isn't complete
+        assert_eq!(Run::from_keystrokes(&keystrokes[..3], &skipped, 8), None);
+        // nor is one that starts after the beginning
+        assert_eq!(Run::from_keystrokes(&keystrokes[1..], &skipped, 8), None);
+    }
+}
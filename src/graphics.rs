@@ -0,0 +1,203 @@
+use std::{cell::OnceCell, collections::BTreeMap, fmt::Write as _, str::FromStr};
+
+use base64::Engine as _;
+use image::{imageops::FilterType, RgbaImage};
+
+/// Assumed size of a terminal cell in pixels. Terminals can't portably be
+/// asked for this, so it only decides how many rows an image takes up and how
+/// far it's scaled down before being sent.
+const CELL_SIZE: (u32, u32) = (10, 20);
+
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Deletes every kitty placement on screen while keeping the image data.
+pub const KITTY_CLEAR: &str = "\x1b_Ga=d,d=a,q=2\x1b\\";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+    /// No graphics support, images are replaced by their alt text.
+    Text,
+}
+
+impl Protocol {
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            Self::Kitty
+        } else if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+            Self::Sixel
+        } else {
+            Self::Text
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kitty" => Ok(Self::Kitty),
+            "sixel" => Ok(Self::Sixel),
+            "none" | "text" => Ok(Self::Text),
+            _ => anyhow::bail!("unknown graphics protocol `{s}` (expected kitty, sixel or none)"),
+        }
+    }
+}
+
+pub struct Picture {
+    image: RgbaImage,
+    cols: u16,
+    rows: u16,
+    sixel: OnceCell<String>,
+}
+
+impl Picture {
+    /// Decodes `data` and scales it down to fit within `max_cols` by
+    /// `max_rows` cells.
+    pub fn new(data: &[u8], max_cols: u16, max_rows: u16) -> anyhow::Result<Self> {
+        let image = image::load_from_memory(data)?.to_rgba8();
+        let (w, h) = image.dimensions();
+        anyhow::ensure!(w > 0 && h > 0, "empty image");
+
+        let scale = [
+            1.0,
+            (max_cols as u32 * CELL_SIZE.0) as f64 / w as f64,
+            (max_rows as u32 * CELL_SIZE.1) as f64 / h as f64,
+        ]
+        .into_iter()
+        .fold(f64::INFINITY, f64::min);
+        let (w, h) = (
+            ((w as f64 * scale) as u32).max(1),
+            ((h as f64 * scale) as u32).max(1),
+        );
+        let image = match image.dimensions() == (w, h) {
+            true => image,
+            false => image::imageops::resize(&image, w, h, FilterType::Triangle),
+        };
+
+        Ok(Self {
+            image,
+            cols: w.div_ceil(CELL_SIZE.0).min(max_cols as u32) as u16,
+            rows: h.div_ceil(CELL_SIZE.1).min(max_rows as u32) as u16,
+            sixel: OnceCell::new(),
+        })
+    }
+
+    pub fn cols(&self) -> u16 {
+        self.cols
+    }
+
+    pub fn rows(&self) -> u16 {
+        self.rows
+    }
+
+    /// Uploads the image to the terminal under `id`, without displaying it.
+    pub fn kitty_transmit(&self, id: u32) -> String {
+        let data = base64::engine::general_purpose::STANDARD.encode(self.image.as_raw());
+        let (w, h) = self.image.dimensions();
+        let mut out = String::with_capacity(data.len() + data.len() / KITTY_CHUNK_SIZE * 16);
+        let count = data.len().div_ceil(KITTY_CHUNK_SIZE);
+        for (i, chunk) in data.as_bytes().chunks(KITTY_CHUNK_SIZE).enumerate() {
+            let more = u8::from(i + 1 < count);
+            match i {
+                0 => write!(out, "\x1b_Ga=t,i={id},f=32,s={w},v={h},q=2,m={more};"),
+                _ => write!(out, "\x1b_Gm={more};"),
+            }
+            .unwrap();
+            // base64 is ascii
+            out.push_str(std::str::from_utf8(chunk).unwrap());
+            out.push_str("\x1b\\");
+        }
+        out
+    }
+
+    /// Displays a previously transmitted image at the cursor.
+    pub fn kitty_place(&self, id: u32) -> String {
+        format!(
+            "\x1b_Ga=p,i={id},c={},r={},C=1,q=2\x1b\\",
+            self.cols, self.rows
+        )
+    }
+
+    pub fn sixel(&self) -> &str {
+        self.sixel.get_or_init(|| encode_sixel(&self.image))
+    }
+}
+
+/// Encodes an image as sixel using a fixed 6x6x6 colour cube. Mostly
+/// transparent pixels are left as the terminal background.
+fn encode_sixel(image: &RgbaImage) -> String {
+    fn level(v: u8) -> usize {
+        (v as usize * 5 + 127) / 255
+    }
+    let colour = |x: u32, y: u32| {
+        let p = image.get_pixel(x, y);
+        (p[3] >= 128).then(|| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]))
+    };
+
+    let (w, h) = image.dimensions();
+    let mut out = format!("\x1bP0;1q\"1;1;{w};{h}");
+
+    let mut used = [false; 216];
+    for (x, y, _) in image.enumerate_pixels() {
+        if let Some(c) = colour(x, y) {
+            used[c] = true;
+        }
+    }
+    for (c, _) in used.iter().enumerate().filter(|(_, &u)| u) {
+        let [r, g, b] = [c / 36, c / 6 % 6, c % 6].map(|l| l * 20);
+        write!(out, "#{c};2;{r};{g};{b}").unwrap();
+    }
+
+    for top in (0..h).step_by(6) {
+        let mut band: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for dy in 0..6.min(h - top) {
+            for x in 0..w {
+                if let Some(c) = colour(x, top + dy) {
+                    band.entry(c).or_insert_with(|| vec![0; w as usize])[x as usize] |= 1 << dy;
+                }
+            }
+        }
+        for (c, bits) in band {
+            write!(out, "#{c}").unwrap();
+            let mut run = bits.iter().peekable();
+            while let Some(&b) = run.next() {
+                let mut count = 1;
+                while run.next_if_eq(&&b).is_some() {
+                    count += 1;
+                }
+                let ch = char::from(63 + b);
+                match count {
+                    1..=3 => (0..count).for_each(|_| out.push(ch)),
+                    _ => write!(out, "!{count}{ch}").unwrap(),
+                }
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_encodes_sixel_runs() {
+        let image = RgbaImage::from_pixel(8, 2, image::Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            encode_sixel(&image),
+            "\x1bP0;1q\"1;1;8;2#180;2;100;0;0#180!8B$-\x1b\\"
+        );
+    }
+}
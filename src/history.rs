@@ -0,0 +1,296 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    io::{self, Write as _},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context as _;
+
+use crate::{
+    epub::state_directory,
+    score::{format_duration, Score, WORD_LEN},
+};
+
+const SPARKS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Sessions shown in the trend of each book.
+const BOOK_TREND: usize = 20;
+
+/// A completed session, as stored in the history.
+///
+/// The history is a tab separated file with one session per line, in the
+/// order of the fields below.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub identifier: String,
+    /// Chapter the session started in.
+    pub chapter: usize,
+    /// Seconds since the Unix epoch when the session ended.
+    pub time: u64,
+    pub duration: Duration,
+    pub chars: usize,
+    pub errors: usize,
+    pub wpm: f64,
+    pub title: String,
+}
+
+impl Entry {
+    pub fn new(identifier: &str, title: &str, chapter: usize, score: &Score) -> Self {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            identifier: identifier.to_owned(),
+            chapter,
+            time: time.as_secs(),
+            duration: score.elapsed,
+            chars: score.keystrokes,
+            errors: score.errors,
+            wpm: score.gross_wpm(),
+            title: title.to_owned(),
+        }
+    }
+
+    /// Appends the session to the history.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = history_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", self.serialize())?;
+        Ok(())
+    }
+
+    fn serialize(&self) -> String {
+        let clean = |s: &str| s.replace(['\t', '\n'], " ");
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{:.1}\t{}",
+            clean(&self.identifier),
+            self.chapter,
+            self.time,
+            self.duration.as_millis(),
+            self.chars,
+            self.errors,
+            self.wpm,
+            clean(&self.title),
+        )
+    }
+
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut fields = line.split('\t');
+        let mut next = || fields.next().context("missing field");
+        Ok(Self {
+            identifier: next()?.to_owned(),
+            chapter: next()?.parse()?,
+            time: next()?.parse()?,
+            duration: Duration::from_millis(next()?.parse()?),
+            chars: next()?.parse()?,
+            errors: next()?.parse()?,
+            wpm: next()?.parse()?,
+            title: next()?.to_owned(),
+        })
+    }
+}
+
+/// Every session in the history, oldest first.
+pub fn load() -> anyhow::Result<Vec<Entry>> {
+    let path = history_path()?;
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    for (i, line) in data.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        match Entry::parse(line) {
+            Ok(entry) => entries.push(entry),
+            // e.g. a line cut short by a crash, which shouldn't hide the rest
+            Err(e) => eprintln!("skipping {}: line {}: {e}", path.display(), i + 1),
+        }
+    }
+    Ok(entries)
+}
+
+/// Totals of a group of sessions.
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    sessions: usize,
+    duration: Duration,
+    chars: usize,
+    errors: usize,
+}
+
+impl Totals {
+    fn add(&mut self, entry: &Entry) {
+        self.sessions += 1;
+        self.duration += entry.duration;
+        self.chars += entry.chars;
+        self.errors += entry.errors;
+    }
+
+    fn wpm(&self) -> f64 {
+        match self.duration.as_secs_f64() {
+            secs if secs > 0.0 => self.chars as f64 / WORD_LEN * 60.0 / secs,
+            _ => 0.0,
+        }
+    }
+
+    fn accuracy(&self) -> f64 {
+        match self.chars {
+            0 => 1.0,
+            n => (n - self.errors.min(n)) as f64 / n as f64,
+        }
+    }
+
+    fn row(&self, label: &str) -> String {
+        format!(
+            "{label:<24}{:>9}{:>9}{:>9}{:>7.1}{:>9.1}%",
+            self.sessions,
+            format_duration(self.duration),
+            self.chars,
+            self.wpm(),
+            self.accuracy() * 100.0,
+        )
+    }
+}
+
+/// Tables of the sessions of the last `days` days and of every book, with the
+/// speed trend of each. Days are in UTC.
+pub fn report(entries: &[Entry], days: usize) -> String {
+    let header = " sessions     time    chars    wpm  accuracy";
+    let mut report = String::new();
+
+    let mut by_day: BTreeMap<u64, Totals> = BTreeMap::new();
+    for entry in entries {
+        by_day.entry(entry.time / 86400).or_default().add(entry);
+    }
+    let recent: Vec<_> = by_day.iter().rev().take(days).rev().collect();
+    writeln!(report, "{:<24}{header}", "day").unwrap();
+    for (&day, totals) in &recent {
+        writeln!(report, "{}", totals.row(&date(day))).unwrap();
+    }
+    let trend: Vec<_> = recent.iter().map(|(_, t)| t.wpm()).collect();
+    writeln!(report, "wpm by day: {}\n", sparkline(&trend)).unwrap();
+
+    // most recently typed books first
+    let mut books: Vec<BookTotals> = Vec::new();
+    for entry in entries.iter().rev() {
+        let i = match books.iter().position(|b| b.identifier == entry.identifier) {
+            Some(i) => i,
+            None => {
+                books.push(BookTotals {
+                    identifier: &entry.identifier,
+                    title: &entry.title,
+                    totals: Totals::default(),
+                    wpm: Vec::new(),
+                });
+                books.len() - 1
+            }
+        };
+        books[i].totals.add(entry);
+        if books[i].wpm.len() < BOOK_TREND {
+            books[i].wpm.push(entry.wpm);
+        }
+    }
+    writeln!(report, "{:<24}{header}  trend", "book").unwrap();
+    for book in &mut books {
+        let title: String = match book.title.chars().count() > 22 {
+            true => book.title.chars().take(21).chain(['…']).collect(),
+            false => book.title.to_owned(),
+        };
+        book.wpm.reverse();
+        writeln!(
+            report,
+            "{}  {}",
+            book.totals.row(&title),
+            sparkline(&book.wpm)
+        )
+        .unwrap();
+    }
+    report
+}
+
+/// Sessions of one book.
+struct BookTotals<'a> {
+    identifier: &'a str,
+    title: &'a str,
+    totals: Totals,
+    /// Speed of the latest sessions, newest first.
+    wpm: Vec<f64>,
+}
+
+/// `values` drawn as bars scaled between their minimum and maximum.
+fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|v| match max - min {
+            range if range > 0.0 => {
+                let level = (v - min) / range * (SPARKS.len() - 1) as f64;
+                SPARKS[level.round() as usize]
+            }
+            _ => SPARKS[SPARKS.len() / 2],
+        })
+        .collect()
+}
+
+/// `YYYY-MM-DD` of a number of days since the Unix epoch.
+fn date(days: u64) -> String {
+    // from Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn history_path() -> anyhow::Result<PathBuf> {
+    let mut path = state_directory()?;
+    path.push("history.tsv");
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_roundtrips_entries() {
+        let entry = Entry {
+            identifier: String::from("urn:isbn:123"),
+            chapter: 3,
+            time: 1_700_000_000,
+            duration: Duration::from_millis(61_500),
+            chars: 420,
+            errors: 7,
+            wpm: 82.0,
+            title: String::from("A\tTitle"),
+        };
+        let parsed = Entry::parse(&entry.serialize()).unwrap();
+        assert_eq!(parsed.title, "A Title");
+        assert_eq!(parsed.duration, entry.duration);
+        assert_eq!(parsed.wpm, entry.wpm);
+    }
+
+    #[test]
+    fn it_formats_dates_and_sparklines() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(19_675), "2023-11-14");
+        assert_eq!(sparkline(&[10.0, 20.0, 15.0]), "▁█▅");
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use roxmltree::{Document, Node};
+
+use crate::{
+    book::{content_identifier, title_from_path, Book},
+    epub::{
+        head_and_body, stylesheets, traverse_document, Align, Author, Content, TextOptions,
+        TocEntry, UNREAD,
+    },
+    repair,
+};
+
+/// A single (X)HTML document, such as a saved web article. Chapters start at
+/// its `h1` and `h2` headings.
+pub struct Html {
+    path: PathBuf,
+    identifier: String,
+    title: String,
+    authors: Vec<Author>,
+    source: String,
+    stylesheets: Vec<String>,
+    chapters: Vec<TocEntry>,
+    /// Ids of the nodes each chapter spans.
+    ranges: Vec<Range<usize>>,
+}
+
+impl Html {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let path = fs::canonicalize(path)?;
+        let data = fs::read(&path)?;
+        Self::from_data(path, &data)
+    }
+
+    /// Reads a document as if it were the file at `path`, which stylesheets
+    /// are found relative to.
+    pub(crate) fn from_data(path: PathBuf, data: &[u8]) -> anyhow::Result<Self> {
+        let source = repair::well_formed(&String::from_utf8_lossy(data)).into_owned();
+        let xml = Document::parse(&source)
+            .with_context(|| format!("failed to parse `{}`", path.display()))?;
+        let (head, body) = head_and_body(&xml)?;
+
+        let dir = path.parent().unwrap_or(Path::new("/")).to_owned();
+        let stylesheets = match head {
+            Some(head) => stylesheets(head, |href| {
+                // stylesheets of saved pages are often missing or online, and
+                // the text is still worth typing without them
+                Ok(match href.contains("://") {
+                    true => String::new(),
+                    false => fs::read_to_string(dir.join(local_path(href))).unwrap_or_default(),
+                })
+            })?,
+            None => Vec::new(),
+        };
+
+        let meta = |name: &str| {
+            head.into_iter()
+                .flat_map(|head| head.children())
+                .find(|n| n.has_tag_name("meta") && n.attribute("name") == Some(name))
+                .and_then(|n| n.attribute("content"))
+        };
+        let authors = meta("author").and_then(Author::parse).into_iter().collect();
+        let identifier = head
+            .into_iter()
+            .flat_map(|head| head.children())
+            .find(|n| n.has_tag_name("link") && n.attribute("rel") == Some("canonical"))
+            .and_then(|n| n.attribute("href"))
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| content_identifier("html", data));
+
+        let headings: Vec<_> = body
+            .descendants()
+            .filter(|n| n.has_tag_name("h1") || n.has_tag_name("h2"))
+            .collect();
+        let title = head
+            .and_then(|head| head.children().find(|n| n.has_tag_name("title")))
+            .or_else(|| headings.iter().copied().find(|n| n.has_tag_name("h1")))
+            .map(text_of)
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| title_from_path(&path));
+
+        let mut chapters = Vec::new();
+        let mut ranges = Vec::new();
+        let first = headings.first().map_or(usize::MAX, |n| n.id().get_usize());
+        // only a heading of its own, if there's anything before the first one
+        let preamble = body
+            .descendants()
+            .take_while(|n| n.id().get_usize() < first)
+            .any(|n| {
+                let unread = || n.ancestors().any(|a| UNREAD.contains(&a.tag_name().name()));
+                (n.is_text() && n.text().is_some_and(|s| !s.trim().is_empty()) && !unread())
+                    || n.has_tag_name("img")
+            });
+        if preamble {
+            let name = match headings.is_empty() {
+                true => title.clone(),
+                false => String::from("Beginning"),
+            };
+            chapters.push(TocEntry::new(name, 0, 0));
+            ranges.push(0..first);
+        }
+        let has_h1 = headings.iter().any(|n| n.has_tag_name("h1"));
+        for (i, heading) in headings.iter().enumerate() {
+            let depth = usize::from(has_h1 && heading.has_tag_name("h2"));
+            chapters.push(TocEntry::new(text_of(*heading), chapters.len(), depth));
+            let end = headings
+                .get(i + 1)
+                .map_or(usize::MAX, |n| n.id().get_usize());
+            ranges.push(heading.id().get_usize()..end);
+        }
+        anyhow::ensure!(!chapters.is_empty(), "`{}` has no text", path.display());
+
+        Ok(Self {
+            path,
+            identifier,
+            title,
+            authors,
+            source,
+            stylesheets,
+            chapters,
+            ranges,
+        })
+    }
+}
+
+impl Book for Html {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn authors(&self) -> &[Author] {
+        &self.authors
+    }
+
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn chapters(&self) -> &[TocEntry] {
+        &self.chapters
+    }
+
+    /// Length of the text in each chapter.
+    fn chapter_sizes(&mut self) -> Vec<u64> {
+        let Ok(xml) = Document::parse(&self.source) else {
+            return vec![0; self.ranges.len()];
+        };
+        let mut sizes = vec![0; self.ranges.len()];
+        for node in xml.descendants().filter(Node::is_text) {
+            let id = node.id().get_usize();
+            if let Some(i) = self.ranges.iter().position(|r| r.contains(&id)) {
+                sizes[i] += node.text().map_or(0, str::len) as u64;
+            }
+        }
+        sizes
+    }
+
+    fn traverse(
+        &mut self,
+        entry: usize,
+        options: &TextOptions,
+        cb: &mut dyn FnMut(Content<'_>, Option<Align>),
+    ) -> anyhow::Result<()> {
+        let xml = Document::parse(&self.source)?;
+        let (_, body) = head_and_body(&xml)?;
+        let range = self.ranges[entry].clone();
+        // notes in other files aren't followed
+        let documents = &mut |_: &str| Ok(None);
+        traverse_document(body, &self.stylesheets, options, cb, Some(range), documents)
+    }
+
+    fn image(&mut self, _entry: usize, href: &str) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(!href.contains("://"), "online image: `{href}`");
+        let dir = self.path.parent().unwrap_or(Path::new("/"));
+        Ok(fs::read(dir.join(local_path(href)))?)
+    }
+}
+
+/// Path of a relative link, without any query or fragment.
+fn local_path(href: &str) -> &str {
+    href.split(['?', '#']).next().unwrap_or(href)
+}
+
+fn text_of(node: Node) -> String {
+    let text: String = node
+        .descendants()
+        .filter(Node::is_text)
+        .filter_map(|n| n.text())
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        epub::test::{options, read_blocks},
+        notes::Notes,
+    };
+
+    #[test]
+    fn it_splits_chapters_at_headings() {
+        let xml = r#"<html><head><title>An Article</title>
+<style>.aside { font-style: italic }</style></head>
+<body><p>Intro.</p>
+<div><h1>One</h1><p>First.</p><h2>One and a half</h2><p class="aside">Aside.</p></div>
+<h1>Two</h1><p>Second.</p></body></html>"#;
+        let mut book = Html::from_data(PathBuf::from("article.html"), xml.as_bytes()).unwrap();
+        assert_eq!(book.title(), "An Article");
+        let names: Vec<_> = book
+            .chapters()
+            .iter()
+            .map(|c| (c.name(), c.depth()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Beginning", 0),
+                ("One", 0),
+                ("One and a half", 1),
+                ("Two", 0)
+            ]
+        );
+
+        let options = options(Notes::Skip);
+        let blocks = read_blocks(|cb| book.traverse(2, &options, cb));
+        let texts: Vec<_> = blocks.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(texts, ["One and a half", "Aside."]);
+    }
+}
@@ -1,4 +1,23 @@
 pub mod backend;
+pub mod book;
+pub mod config;
+pub mod drill;
+pub mod entities;
 pub mod epub;
+pub mod fb2;
+pub mod ghost;
+pub mod graphics;
+pub mod history;
+pub mod html;
+pub mod markdown;
+pub mod notes;
+pub mod plaintext;
+pub mod quotes;
+pub mod repair;
+pub mod replay;
+pub mod score;
+pub mod state;
+pub mod stats;
 pub mod style;
 pub mod term;
+pub mod translit;
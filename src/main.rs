@@ -1,31 +1,33 @@
-use std::{num::NonZeroUsize, path::PathBuf};
+use std::{
+    num::{NonZeroU32, NonZeroUsize},
+    path::PathBuf,
+    time::Duration,
+};
 
 use anyhow::Context;
-use crossterm::event::{self, Event, KeyEvent};
+use crossterm::event::{self, Event};
 use typepub::{
-    epub::{Directory, Epub, SearchBackend},
-    term::Display,
+    backend::ErrorMode,
+    book,
+    config::Config,
+    epub::{Directory, SearchBackend},
+    ghost::Pace,
+    graphics::Protocol,
+    notes::Notes,
+    quotes::Quotes,
+    replay::Log,
+    term::{Display, Goal, Options},
 };
 
 // TODO: features
 // - nicer virtual styling
 // - book select maybe?
-// - progress saving
-// - scorescreen; wpm/acc display at end
-// - score annotations per paragraph
-// - window resize
-// - sixel images
-
-// TODO: annoyances/bugs
-// - british english uses single quotation marks for speech, prefer double--
-//   convert single quotation marks to double quotations? messes up quotes
-//   within quotes, so will have to parse nested and convert accordingly
 
 fn main() -> anyhow::Result<()> {
     xflags::xflags! {
         cmd typepub {
             cmd path {
-                /// Path to book.
+                /// Path to book: EPUB, plain text, Markdown, FB2 or (X)HTML.
                 required path: PathBuf
             }
             cmd search {
@@ -37,53 +39,207 @@ fn main() -> anyhow::Result<()> {
                 ///     Windows: `%HOMEPATH%\Documents\books`
                 optional -l,--library library: PathBuf
             }
+            cmd replay {
+                /// Session log recorded with `--record`.
+                required log: PathBuf
+                /// Playback speed, e.g. `2` for twice as fast.
+                /// Defaults to 1.
+                optional -s,--speed speed: f64
+                /// Book to play the log back in, if it's no longer where it
+                /// was when recorded.
+                optional -b,--book book: PathBuf
+            }
+            cmd stats {
+                /// Number of keys and bigrams to list.
+                /// Defaults to 10.
+                optional -n,--count count: usize
+            }
+            cmd drill {
+                /// Directory of books to take sentences from.
+                /// Defaults to the same directory as `search`.
+                optional -l,--library library: PathBuf
+                /// Number of sentences to type.
+                /// Defaults to 10.
+                optional -n,--sentences sentences: NonZeroUsize
+            }
+            cmd history {
+                /// Number of days to list.
+                /// Defaults to 14.
+                optional -d,--days days: NonZeroUsize
+            }
             /// Width of text view, in characters.
             /// Defaults to 80.
             optional -w,--width width: NonZeroUsize
+            /// How to draw images: `kitty`, `sixel` or `none`.
+            /// Detected from the terminal by default.
+            optional -g,--graphics graphics: Protocol
+            /// Quotation marks: `keep` as written, or `swap` single and double
+            /// for books that use single quotes for speech.
+            /// Remembered for the book once given.
+            optional -q,--quotes quotes: Quotes
+            /// Footnotes and endnotes: `skip` them, type them `inline` in
+            /// brackets, or show them in a `panel` beside the text (or in
+            /// the status line, if the terminal is too narrow).
+            /// Defaults to `skip`.
+            optional --notes notes: Notes
+            /// Accept ASCII transliterations of characters, e.g. `e` for `é`
+            /// or `ss` for `ß`.
+            optional -a,--ascii
+            /// What mistyping does: `advance` past the character, `stop` until
+            /// it's typed correctly, or advance but `correct` every error before
+            /// leaving the paragraph.
+            /// Defaults to `advance`.
+            optional -e,--errors errors: ErrorMode
+            /// Practise for this many minutes instead of a chapter, starting
+            /// from the saved position and carrying on into following chapters.
+            optional -t,--minutes minutes: NonZeroU32
+            /// Practise for this many words instead of a chapter, starting
+            /// from the saved position and carrying on into following chapters.
+            optional --words words: NonZeroUsize
+            /// Record each session's keystrokes to a log in the state
+            /// directory, for `typepub replay`.
+            optional -r,--record
+            /// Race a ghost cursor: `best` for your fastest complete run
+            /// through the chapter, or a speed in words per minute.
+            optional -p,--pace pace: Pace
         }
     };
 
     let args = Typepub::from_env()?;
+    // only loaded when typing, so a broken config doesn't stop the reports
+    let mut config = None;
+    let mut replay = None;
+    let mut drill = None;
     let book = match args.subcommand {
-        TypepubCmd::Path(Path { path }) => Epub::from_path(&path)?,
+        TypepubCmd::Path(Path { path }) => book::open(&path)?,
         TypepubCmd::Search(Search { library, search }) => library
             .map_or_else(Directory::from_home, Directory::from_path)?
             .search(&search)?
             .context("book not found")?,
+        TypepubCmd::Replay(Replay { log, speed, book }) => {
+            let log = Log::load(&log)?;
+            let speed = speed.unwrap_or(1.0);
+            anyhow::ensure!(speed > 0.0, "speed must be positive");
+            let book = book::open(book.as_deref().unwrap_or(&log.book))?;
+            anyhow::ensure!(
+                book.identifier() == log.identifier,
+                "log was recorded in a different book"
+            );
+            let config = config.insert(Config::load()?);
+            if !log.config.is_empty() && log.config != config.fingerprint() {
+                eprintln!("warning: config.ini has changed since the log was recorded");
+            }
+            replay = Some((log, speed));
+            book
+        }
+        TypepubCmd::Stats(Stats { count }) => {
+            let stats = typepub::stats::Stats::load()?;
+            anyhow::ensure!(!stats.is_empty(), "no statistics yet, type something first");
+            print!("{}", stats.report(count.unwrap_or(10)));
+            return Ok(());
+        }
+        TypepubCmd::Drill(Drill { library, sentences }) => {
+            let library = library.map_or_else(Directory::from_home, Directory::from_path)?;
+            let stats = typepub::stats::Stats::load()?;
+            let sentences = sentences.map_or(10, NonZeroUsize::get);
+            let config = config.insert(Config::load()?);
+            let generated = typepub::drill::Drill::generate(&library, &stats, config, sentences)?
+                .context("no weak bigrams to drill yet, type some more first")?;
+            drill = Some(generated.text);
+            book::open(&generated.book)?
+        }
+        TypepubCmd::History(History { days }) => {
+            let entries = typepub::history::load()?;
+            anyhow::ensure!(!entries.is_empty(), "no sessions completed yet");
+            let days = days.map_or(14, NonZeroUsize::get);
+            print!("{}", typepub::history::report(&entries, days));
+            return Ok(());
+        }
+    };
+    let config = match config {
+        Some(config) => config,
+        None => Config::load()?,
     };
 
     let width = args
         .width
         .and_then(|x| x.get().try_into().ok())
         .unwrap_or(80u16);
+    let goal = match (args.minutes, args.words) {
+        (Some(_), Some(_)) => anyhow::bail!("`--minutes` and `--words` can't be used together"),
+        (Some(minutes), None) => Goal::Time(Duration::from_secs(60 * u64::from(minutes.get()))),
+        (None, Some(words)) => Goal::Words(words.get()),
+        (None, None) => Goal::Chapter,
+    };
+    let options = Options {
+        width,
+        graphics: args.graphics.unwrap_or_else(Protocol::detect),
+        quotes: args.quotes,
+        notes: args.notes.unwrap_or_default(),
+        transliterate: args.ascii,
+        errors: args.errors.unwrap_or_default(),
+        goal,
+        record: args.record,
+        pace: args.pace,
+    };
 
-    println!("{}'s {}", book.author().unwrap(), book.name());
+    match book.author() {
+        Some(author) => println!("{author}'s {}", book.title()),
+        None => println!("{}", book.title()),
+    }
 
     let (term_w, term_h) = crossterm::terminal::size()?;
 
     let mut w = std::io::stdout();
-    let mut display = Display::new(book, width, term_w, term_h);
+    let mut display = Display::new(book, options, config, term_w, term_h);
+    if let Some((log, speed)) = replay {
+        display.play(log, speed);
+    }
+    if let Some(text) = drill {
+        display.drill(text);
+    }
+
+    // the terminal is restored before any error is reported
+    let result = display
+        .enter(&mut w)
+        .and_then(|()| run(&mut display, &mut w));
+    display.exit(&mut w)?;
+    result?;
+    for log in display.recorded_logs() {
+        println!("session recorded to {}", log.display());
+    }
 
-    display.enter(&mut w)?;
+    Ok(())
+}
 
+fn run(display: &mut Display, w: &mut impl std::io::Write) -> anyhow::Result<()> {
     loop {
-        let ev = next_key_event()?;
-        if display.handle_input(ev)? {
-            break;
+        match next_event(display.tick_rate())? {
+            Some(Event::Key(ev)) => {
+                if display.handle_input(ev)? {
+                    return Ok(());
+                }
+            }
+            Some(Event::Resize(term_w, term_h)) => display.resize(term_w, term_h),
+            Some(_) => continue,
+            None => display.tick()?,
         }
 
-        display.render(&mut w)?;
+        display.render(w)?;
     }
-
-    display.exit(&mut w)?;
-
-    Ok(())
 }
 
-fn next_key_event() -> anyhow::Result<KeyEvent> {
+/// Waits for the next key or resize event, or returns `None` once `timeout`
+/// passes without one.
+fn next_event(timeout: Option<Duration>) -> anyhow::Result<Option<Event>> {
     loop {
-        if let Ok(Event::Key(event)) = event::read() {
-            return Ok(event);
+        if let Some(timeout) = timeout {
+            if !event::poll(timeout)? {
+                return Ok(None);
+            }
+        }
+        if let Ok(event @ (Event::Key(_) | Event::Resize(..))) = event::read() {
+            return Ok(Some(event));
         }
     }
 }
@@ -0,0 +1,465 @@
+use std::{fs, path::Path};
+
+use crate::{
+    book::{content_identifier, title_from_path, Block, Parsed},
+    epub::Author,
+    style::Style,
+};
+
+type Chapter = (String, usize, Vec<Block>);
+
+/// A block of Markdown before its inline markup is read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Raw {
+    Heading(usize, String),
+    Paragraph(String),
+    Quote(String),
+    /// Fenced code, which has no inline markup.
+    Code(String),
+}
+
+/// Reads a Markdown book. Chapters start at the highest level of heading
+/// that's used more than once, and a `title` and `author` can be given in
+/// front matter.
+pub(crate) fn load(path: &Path) -> anyhow::Result<Parsed> {
+    let data = fs::read(path)?;
+    let text = String::from_utf8_lossy(&data);
+    let (title, authors, chapters) = parse(&text);
+    Ok(Parsed::new(
+        path,
+        content_identifier("md", &data),
+        title.unwrap_or_else(|| title_from_path(path)),
+        authors,
+        chapters,
+    ))
+}
+
+fn parse(text: &str) -> (Option<String>, Vec<Author>, Vec<Chapter>) {
+    let (mut title, authors, body) = front_matter(text);
+    let raw = blocks(body);
+
+    let count = |level| {
+        raw.iter()
+            .filter(|b| matches!(b, Raw::Heading(l, _) if *l == level))
+            .count()
+    };
+    let levels: Vec<usize> = (1..=6).filter(|&l| count(l) > 0).collect();
+    let chapter_level = levels
+        .iter()
+        .copied()
+        .find(|&l| count(l) > 1)
+        .or(levels.first().copied());
+    // a lone heading above the chapters is the book's title
+    if title.is_none() && levels.first() < chapter_level.as_ref() {
+        title = raw.iter().find_map(|b| match b {
+            Raw::Heading(l, s) if Some(*l) == levels.first().copied() => Some(plain(s)),
+            _ => None,
+        });
+    }
+
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for block in raw {
+        let (blocks, images) = match &block {
+            Raw::Heading(level, s) => {
+                let (runs, images) = inline(s);
+                if Some(*level) == chapter_level {
+                    chapters.push((plain(s), 0, Vec::new()));
+                }
+                (vec![Block::Header(runs)], images)
+            }
+            Raw::Paragraph(s) => {
+                let (runs, images) = inline(s);
+                (vec![Block::Paragraph(runs)], images)
+            }
+            Raw::Quote(s) => {
+                let (runs, images) = inline(s);
+                (vec![Block::Quote(runs)], images)
+            }
+            Raw::Code(s) => (vec![Block::Preformatted(s.clone())], Vec::new()),
+        };
+        if chapters.is_empty() {
+            let name = match &block {
+                Raw::Heading(_, s) => plain(s),
+                _ => String::from("Beginning"),
+            };
+            chapters.push((name, 0, Vec::new()));
+        }
+        let (_, _, content) = chapters.last_mut().unwrap();
+        content.extend(blocks.into_iter().filter(|b| match b {
+            Block::Header(runs) | Block::Paragraph(runs) => !runs.is_empty(),
+            _ => true,
+        }));
+        content.extend(images);
+    }
+    (title, authors, chapters)
+}
+
+/// Splits off YAML front matter, reading the title and author from it.
+fn front_matter(text: &str) -> (Option<String>, Vec<Author>, &str) {
+    let mut title = None;
+    let mut authors = Vec::new();
+    let Some(rest) = text.strip_prefix("---\n") else {
+        return (title, authors, text);
+    };
+    let Some((matter, body)) = rest.split_once("\n---\n") else {
+        return (title, authors, text);
+    };
+    for line in matter.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        match key.trim() {
+            "title" => title = Some(value.to_owned()),
+            "author" => authors.extend(Author::parse(value)),
+            _ => {}
+        }
+    }
+    (title, authors, body)
+}
+
+fn blocks(text: &str) -> Vec<Raw> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut fence: Option<(&str, Vec<&str>)> = None;
+
+    fn flush(blocks: &mut Vec<Raw>, lines: &mut Vec<&str>, quote: bool) {
+        if lines.is_empty() {
+            return;
+        }
+        let text = lines.join("\n");
+        lines.clear();
+        blocks.push(match quote {
+            true => Raw::Quote(text),
+            false => Raw::Paragraph(text),
+        });
+    }
+
+    for line in text.lines() {
+        if let Some((marker, lines)) = &mut fence {
+            match line.trim_start().starts_with(*marker) {
+                true => {
+                    blocks.push(Raw::Code(lines.join("\n")));
+                    fence = None;
+                }
+                false => lines.push(line),
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            flush(&mut blocks, &mut paragraph, false);
+            flush(&mut blocks, &mut quote, true);
+            fence = Some((marker, Vec::new()));
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            flush(&mut blocks, &mut paragraph, false);
+            let rest = rest.strip_prefix(' ').unwrap_or(rest);
+            match rest.trim().is_empty() {
+                true => flush(&mut blocks, &mut quote, true),
+                false => quote.push(rest),
+            }
+            continue;
+        }
+        flush(&mut blocks, &mut quote, true);
+        if trimmed.is_empty() {
+            flush(&mut blocks, &mut paragraph, false);
+            continue;
+        }
+        // setext headings underline the paragraph before them
+        if !paragraph.is_empty() && is_underline(trimmed, '=') {
+            let text = paragraph.join(" ");
+            paragraph.clear();
+            blocks.push(Raw::Heading(1, text));
+            continue;
+        }
+        if !paragraph.is_empty() && is_underline(trimmed, '-') {
+            let text = paragraph.join(" ");
+            paragraph.clear();
+            blocks.push(Raw::Heading(2, text));
+            continue;
+        }
+        if is_rule(trimmed) {
+            flush(&mut blocks, &mut paragraph, false);
+            continue;
+        }
+        if let Some((level, text)) = atx_heading(trimmed) {
+            flush(&mut blocks, &mut paragraph, false);
+            blocks.push(Raw::Heading(level, text.to_owned()));
+            continue;
+        }
+        if let Some(item) = list_item(trimmed) {
+            flush(&mut blocks, &mut paragraph, false);
+            paragraph.push(item);
+            continue;
+        }
+        paragraph.push(trimmed);
+    }
+    if let Some((_, lines)) = fence {
+        blocks.push(Raw::Code(lines.join("\n")));
+    }
+    flush(&mut blocks, &mut paragraph, false);
+    flush(&mut blocks, &mut quote, true);
+    blocks
+}
+
+fn is_underline(line: &str, c: char) -> bool {
+    line.chars().all(|l| l == c)
+}
+
+fn is_rule(line: &str) -> bool {
+    ['*', '-', '_'].into_iter().any(|c| {
+        line.chars().filter(|&l| l == c).count() >= 3 && line.chars().all(|l| l == c || l == ' ')
+    })
+}
+
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    // closing hashes are optional
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, text))
+}
+
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.strip_prefix(' ').map(str::trim_start);
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..]
+        .strip_prefix(['.', ')'])
+        .and_then(|rest| rest.strip_prefix(' '))
+        .map(str::trim_start)
+}
+
+/// Text of inline Markdown without any of its markup.
+fn plain(s: &str) -> String {
+    inline(s).0.into_iter().map(|(s, _)| s).collect()
+}
+
+/// Styled runs of inline Markdown, along with the images in it.
+fn inline(s: &str) -> (Vec<(String, Style)>, Vec<Block>) {
+    let chars: Vec<char> = s.chars().collect();
+    let mut runs: Vec<(String, Style)> = Vec::new();
+    let mut images = Vec::new();
+    let mut style = Style::empty();
+    // the delimiter each style was opened with
+    let mut italic = None;
+    let mut bold = None;
+
+    let push = |runs: &mut Vec<(String, Style)>, s: &str, style: Style| match runs.last_mut() {
+        Some((text, last)) if *last == style => text.push_str(s),
+        _ => runs.push((s.to_owned(), style)),
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(char::is_ascii_punctuation) => {
+                push(&mut runs, &chars[i + 1].to_string(), style);
+                i += 2;
+            }
+            '`' => {
+                let n = run_len(&chars, i, '`');
+                let close = (i + n..chars.len()).find(|&j| run_len(&chars, j, '`') == n);
+                match close {
+                    Some(j) => {
+                        let code: String = chars[i + n..j].iter().collect();
+                        push(&mut runs, code.trim(), style | Style::MONOSPACE);
+                        i = j + n;
+                    }
+                    None => {
+                        push(&mut runs, &"`".repeat(n), style);
+                        i += n;
+                    }
+                }
+            }
+            '!' | '[' => {
+                let start = i + usize::from(c == '!');
+                match link(&chars, start).filter(|_| chars[start] == '[') {
+                    Some((text, src, end)) if c == '!' => {
+                        images.push(Block::Image(src, Some(text).filter(|t| !t.is_empty())));
+                        i = end;
+                    }
+                    Some((text, _, end)) => {
+                        for (s, inner) in inline(&text).0 {
+                            push(&mut runs, &s, style | inner);
+                        }
+                        i = end;
+                    }
+                    None => {
+                        push(&mut runs, &c.to_string(), style);
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '_' => {
+                let n = run_len(&chars, i, c);
+                let prev = i.checked_sub(1).map(|p| chars[p]);
+                let next = chars.get(i + n).copied();
+                let can_open = next.is_some_and(|n| !n.is_whitespace());
+                let can_close = prev.is_some_and(|p| !p.is_whitespace());
+                let intraword = c == '_'
+                    && prev.is_some_and(char::is_alphanumeric)
+                    && next.is_some_and(char::is_alphanumeric);
+                let mut toggled = false;
+                if !intraword {
+                    for (len, flag, open) in
+                        [(2, Style::BOLD, &mut bold), (1, Style::ITALIC, &mut italic)]
+                    {
+                        if n != len && n != 3 {
+                            continue;
+                        }
+                        if *open == Some(c) && can_close {
+                            *open = None;
+                            style.remove(flag);
+                            toggled = true;
+                        } else if open.is_none() && can_open && has_closer(&chars, i + n, c, n) {
+                            *open = Some(c);
+                            style.insert(flag);
+                            toggled = true;
+                        }
+                    }
+                }
+                if !toggled {
+                    push(&mut runs, &c.to_string().repeat(n), style);
+                }
+                i += n;
+            }
+            c => {
+                push(&mut runs, &c.to_string(), style);
+                i += 1;
+            }
+        }
+    }
+    (runs, images)
+}
+
+fn run_len(chars: &[char], i: usize, c: char) -> usize {
+    chars[i..].iter().take_while(|&&d| d == c).count()
+}
+
+/// Whether a run of `n` of `c` that can close emphasis follows `from`.
+fn has_closer(chars: &[char], from: usize, c: char, n: usize) -> bool {
+    let mut j = from;
+    while j < chars.len() {
+        let len = run_len(chars, j, c);
+        if len == 0 {
+            j += 1;
+            continue;
+        }
+        if len >= n && !chars[j - 1].is_whitespace() {
+            return true;
+        }
+        j += len;
+    }
+    false
+}
+
+/// `[text](destination)` starting at `start`, with the index after it.
+fn link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut close = None;
+    for (j, &c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(j);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = chars[close + 2..].iter().position(|&c| c == ')')? + close + 2;
+    let text = chars[start + 1..close].iter().collect();
+    let destination: String = chars[close + 2..end].iter().collect();
+    // a title may follow the destination
+    let destination = destination.split_whitespace().next().unwrap_or_default();
+    Some((text, destination.to_owned(), end + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        book::Book,
+        epub::{test::options, Content},
+        notes::Notes,
+    };
+
+    #[test]
+    fn it_reads_inline_markup() {
+        let (runs, images) = inline("a *b* **c** _d_ snake_case `*e*` [f](g) ![h](i.png)");
+        assert_eq!(
+            runs,
+            [
+                (String::from("a "), Style::empty()),
+                (String::from("b"), Style::ITALIC),
+                (String::from(" "), Style::empty()),
+                (String::from("c"), Style::BOLD),
+                (String::from(" "), Style::empty()),
+                (String::from("d"), Style::ITALIC),
+                (String::from(" snake_case "), Style::empty()),
+                (String::from("*e*"), Style::MONOSPACE),
+                (String::from(" f "), Style::empty()),
+            ]
+        );
+        assert_eq!(
+            images,
+            [Block::Image(String::from("i.png"), Some(String::from("h")))]
+        );
+        assert_eq!(plain("2 * 3 = 6, *not closed"), "2 * 3 = 6, *not closed");
+    }
+
+    #[test]
+    fn it_splits_chapters_at_repeated_headings() {
+        let text = "---\ntitle: \"Book\"\n---\n# Book\n\nIntro\n\n## One\n\nText\nmore text\n\n> quoted\n\n## Two\n\n- item\n";
+        let (title, _, chapters) = parse(text);
+        assert_eq!(title.as_deref(), Some("Book"));
+        let names: Vec<_> = chapters.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(names, ["Book", "One", "Two"]);
+        assert_eq!(chapters[1].2.len(), 3);
+        assert!(matches!(chapters[1].2[2], Block::Quote(_)));
+    }
+
+    #[test]
+    fn it_keeps_code_preformatted() {
+        let text = "Run it:\n\n```\nfn main() {\n    run();\n}\n```\n";
+        let (_, _, chapters) = parse(text);
+        let code = "fn main() {\n    run();\n}";
+        assert_eq!(chapters[0].2[1], Block::Preformatted(String::from(code)));
+
+        let mut book = Parsed::new(
+            Path::new("book.md"),
+            String::new(),
+            String::new(),
+            Vec::new(),
+            chapters,
+        );
+        let options = options(Notes::Skip);
+        let mut preformatted = Vec::new();
+        book.traverse(0, &options, &mut |content, _| {
+            if let Content::Preformatted(s, _) = content {
+                preformatted.push(s.to_owned());
+            }
+        })
+        .unwrap();
+        assert_eq!(preformatted, [code]);
+    }
+}
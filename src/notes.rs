@@ -0,0 +1,149 @@
+//! Footnotes and endnotes: recognising them in a document, and how they're
+//! read.
+
+use std::str::FromStr;
+
+use roxmltree::Node;
+
+use crate::epub::is_inline;
+
+const OPS: &str = "http://www.idpf.org/2007/ops";
+
+/// What happens to the notes a book's text refers to.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Notes {
+    /// Left out, along with the references to them.
+    #[default]
+    Skip,
+    /// Typed in brackets where they're referred to.
+    Inline,
+    /// Shown beside the paragraph referring to them, without being typed.
+    Panel,
+}
+
+impl Notes {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Inline => "inline",
+            Self::Panel => "panel",
+        }
+    }
+}
+
+impl FromStr for Notes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "inline" => Ok(Self::Inline),
+            "panel" => Ok(Self::Panel),
+            _ => anyhow::bail!("unknown notes option `{s}` (expected skip, inline or panel)"),
+        }
+    }
+}
+
+/// Semantic types of `node`, from its `epub:type` and ARIA role.
+fn types<'a>(node: Node<'a, '_>) -> impl Iterator<Item = &'a str> {
+    node.attribute((OPS, "type"))
+        .into_iter()
+        .chain(node.attribute("role"))
+        .flat_map(str::split_whitespace)
+}
+
+/// Whether `node` links to a note: marked as doing so, or a short link in
+/// superscript, as books without semantic markup write them.
+pub(crate) fn is_reference(node: Node) -> bool {
+    if !node.has_tag_name("a") {
+        return false;
+    }
+    if types(node).any(|t| matches!(t, "noteref" | "doc-noteref")) {
+        return true;
+    }
+    let superscript = node
+        .ancestors()
+        .skip(1)
+        .take(2)
+        .any(|n| n.has_tag_name("sup"))
+        || node.descendants().any(|n| n.has_tag_name("sup"));
+    let short = text(node, None).chars().count() <= 4;
+    node.attribute("href")
+        .is_some_and(|href| href.contains('#'))
+        && superscript
+        && short
+}
+
+/// Whether `node` is marked as a note.
+pub(crate) fn is_note(node: Node) -> bool {
+    types(node).any(|t| {
+        matches!(
+            t,
+            "footnote" | "endnote" | "rearnote" | "note" | "doc-footnote" | "doc-endnote"
+        )
+    })
+}
+
+/// The note a link's target is in. Targets are often an anchor at the start
+/// of the note's paragraph rather than the note itself.
+pub(crate) fn note_of<'a, 'input>(target: Node<'a, 'input>) -> Node<'a, 'input> {
+    if let Some(note) = target.ancestors().find(|&n| is_note(n)) {
+        return note;
+    }
+    let mut note = target;
+    while is_inline(note) {
+        match note.parent_element() {
+            Some(parent) if !parent.has_tag_name("body") => note = parent,
+            _ => break,
+        }
+    }
+    note
+}
+
+/// Text of `node` with whitespace collapsed, leaving out any link back to
+/// the reference with id `reference`.
+pub(crate) fn text(node: Node, reference: Option<&str>) -> String {
+    let is_backlink = |a: Node| {
+        a.has_tag_name("a")
+            && (types(a).any(|t| matches!(t, "backlink" | "doc-backlink"))
+                || reference.is_some_and(|id| {
+                    a.attribute("href")
+                        .is_some_and(|href| href.split_once('#').is_some_and(|(_, f)| f == id))
+                }))
+    };
+    let text: String = node
+        .descendants()
+        .filter(|n| n.is_text() && !n.ancestors().any(is_backlink))
+        .filter_map(|n| n.text())
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_finds_notes_of_references() {
+        let xml = r##"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<p>Text.<a id="r1" epub:type="noteref" href="#n1">1</a> More.<sup><a id="r2" href="#n2">2</a></sup></p>
+<aside id="n1" epub:type="footnote"><p>First note.</p></aside>
+<p><a id="n2" href="#r2">[2]</a> Second note.</p>
+</body></html>"##;
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let references: Vec<_> = doc.descendants().filter(|&n| is_reference(n)).collect();
+        assert_eq!(references.len(), 2);
+        let notes: Vec<_> = references
+            .iter()
+            .map(|r| {
+                let id = r.attribute("href").unwrap().trim_start_matches('#');
+                let target = doc
+                    .descendants()
+                    .find(|n| n.attribute("id") == Some(id))
+                    .unwrap();
+                text(note_of(target), r.attribute("id"))
+            })
+            .collect();
+        assert_eq!(notes, ["First note.", "Second note."]);
+    }
+}
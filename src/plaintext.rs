@@ -0,0 +1,125 @@
+use std::{fs, path::Path};
+
+use regex::Regex;
+
+use crate::{
+    book::{content_identifier, title_from_path, Block, Parsed},
+    epub::Author,
+    style::Style,
+};
+
+/// Headings that start a chapter in a plain text book.
+const HEADING: &str = r"(?i)^((chapter|book|part|volume|prologue|epilogue|preface|introduction|afterword)\b.*|[IVXLC]+\.?|\d+\.?)$";
+/// Lines longer than this aren't taken for headings.
+const MAX_HEADING_LEN: usize = 60;
+/// Blank lines separating chapters, when a book has no headings.
+const CHAPTER_BREAK: usize = 3;
+
+/// Reads a plain text book, with paragraphs separated by blank lines.
+///
+/// Chapters start at lines that look like headings (`Chapter 1`, `IV`, ...),
+/// or failing that at runs of several blank lines. Project Gutenberg style
+/// `Title:` and `Author:` lines give the book's metadata.
+pub(crate) fn load(path: &Path) -> anyhow::Result<Parsed> {
+    let data = fs::read(path)?;
+    let text = String::from_utf8_lossy(&data);
+    let (title, authors, chapters) = parse(&text);
+    Ok(Parsed::new(
+        path,
+        content_identifier("txt", &data),
+        title.unwrap_or_else(|| title_from_path(path)),
+        authors,
+        chapters,
+    ))
+}
+
+type Chapter = (String, usize, Vec<Block>);
+
+fn parse(text: &str) -> (Option<String>, Vec<Author>, Vec<Chapter>) {
+    let heading = Regex::new(HEADING).unwrap();
+    let mut title = None;
+    let mut authors = Vec::new();
+    // paragraphs, along with the blank lines before each
+    let mut paragraphs: Vec<(usize, Vec<&str>)> = Vec::new();
+    let mut blank = 0;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        // metadata lines aren't typed
+        if let Some(value) = line.strip_prefix("Title:").filter(|_| title.is_none()) {
+            title = Some(value.trim().to_owned());
+            continue;
+        } else if let Some(value) = line.strip_prefix("Author:").filter(|_| authors.is_empty()) {
+            authors.extend(Author::parse(value));
+            continue;
+        }
+        match paragraphs.last_mut() {
+            Some((_, lines)) if blank == 0 => lines.push(line),
+            _ => paragraphs.push((blank, vec![line])),
+        }
+        blank = 0;
+    }
+
+    let is_heading = |lines: &[&str]| {
+        lines.len() == 1 && lines[0].len() <= MAX_HEADING_LEN && heading.is_match(lines[0])
+    };
+    let by_heading = paragraphs.iter().any(|(_, lines)| is_heading(lines));
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for (i, (blank, lines)) in paragraphs.iter().enumerate() {
+        let starts_chapter = match by_heading {
+            true => is_heading(lines),
+            false => i == 0 || *blank >= CHAPTER_BREAK,
+        };
+        let runs = vec![(lines.join(" "), Style::empty())];
+        if starts_chapter {
+            let name = match by_heading {
+                true => lines[0].to_owned(),
+                false => format!("Part {}", chapters.len() + 1),
+            };
+            let block = match by_heading {
+                true => Block::Header(runs),
+                false => Block::Paragraph(runs),
+            };
+            chapters.push((name, 0, vec![block]));
+            continue;
+        }
+        match chapters.last_mut() {
+            Some((_, _, blocks)) => blocks.push(Block::Paragraph(runs)),
+            // text before the first heading
+            None => chapters.push((String::from("Beginning"), 0, vec![Block::Paragraph(runs)])),
+        }
+    }
+    (title, authors, chapters)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_splits_chapters_at_headings() {
+        let text = "Title: A Book\nAuthor: Jane Doe\n\nCHAPTER I\n\nIt was a dark\nand stormy night.\n\nII.\n\nThe end.\n";
+        let (title, authors, chapters) = parse(text);
+        assert_eq!(title.as_deref(), Some("A Book"));
+        assert_eq!(authors.len(), 1);
+        let names: Vec<_> = chapters.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(names, ["CHAPTER I", "II."]);
+        assert_eq!(
+            chapters[0].2[1],
+            Block::Paragraph(vec![(
+                String::from("It was a dark and stormy night."),
+                Style::empty()
+            )])
+        );
+    }
+
+    #[test]
+    fn it_splits_chapters_at_blank_lines() {
+        let (_, _, chapters) = parse("One.\n\nTwo.\n\n\n\nThree.\n");
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].2.len(), 2);
+    }
+}
@@ -0,0 +1,183 @@
+use std::str::FromStr;
+
+/// Words commonly written with a leading apostrophe, which would otherwise look
+/// like the start of a quotation.
+const ELISIONS: &[&str] = &[
+    "bout", "cause", "em", "ere", "kay", "n", "neath", "nother", "round", "til", "tis", "twas",
+    "twere", "twill",
+];
+
+/// How quotation marks in a book's text are presented.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Quotes {
+    /// As written in the book.
+    #[default]
+    Keep,
+    /// Single and double quotation marks exchanged, for books that use single
+    /// quotes for speech.
+    Swap,
+}
+
+impl Quotes {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Swap => "swap",
+        }
+    }
+}
+
+impl FromStr for Quotes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "swap" => Ok(Self::Swap),
+            _ => anyhow::bail!("unknown quotes option `{s}` (expected keep or swap)"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mark {
+    Open,
+    Close,
+    Apostrophe,
+}
+
+/// Swaps single and double quotation marks within a block of text, leaving
+/// apostrophes alone, so that speech in single quotes reads with double quotes
+/// and quotes nested within it with single ones.
+///
+/// Every swapped pair has the same encoded length, so positions in the text
+/// (and styling built from them) stay valid.
+pub fn swap(text: &mut String) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut depth = 0usize;
+    let mut swapped = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let swap = match c {
+            '“' => '‘',
+            '”' => '’',
+            '"' => '\'',
+            '‘' | '’' | '\'' => {
+                let mark = classify(&chars, i, depth);
+                match mark {
+                    Mark::Open => depth += 1,
+                    Mark::Close => depth -= 1,
+                    Mark::Apostrophe => {}
+                }
+                match (mark, c) {
+                    (Mark::Apostrophe, c) => c,
+                    (_, '‘') => '“',
+                    (_, '’') => '”',
+                    _ => '"',
+                }
+            }
+            c => c,
+        };
+        swapped.push(swap);
+    }
+    *text = swapped;
+}
+
+fn classify(chars: &[char], i: usize, depth: usize) -> Mark {
+    let prev = i.checked_sub(1).map(|i| chars[i]);
+    let next = chars.get(i + 1).copied();
+    let word_before = prev.is_some_and(char::is_alphanumeric);
+    let word_after = next.is_some_and(char::is_alphanumeric);
+
+    if word_before && word_after {
+        return Mark::Apostrophe;
+    }
+    let after_space = match prev {
+        Some(p) => p.is_whitespace(),
+        None => true,
+    };
+    let opening_position = after_space || prev.is_some_and(|p| "([{—–-“\"".contains(p));
+    if opening_position && next.is_some_and(|n| !n.is_whitespace()) {
+        // a typographically correct `’` at the start of a word is an elision
+        return match chars[i] == '’' || is_elision(&chars[i + 1..]) {
+            true => Mark::Apostrophe,
+            false => Mark::Open,
+        };
+    }
+    if depth == 0 || chars[i] == '‘' || after_space {
+        return Mark::Apostrophe;
+    }
+    // a plural possessive (`the boys’ toys`) inside a quotation is only told
+    // apart from the end of it by what follows: another closing mark before
+    // any opening one means this isn't the end
+    if prev == Some('s') {
+        for (j, &c) in chars.iter().enumerate().skip(i + 1) {
+            if matches!(c, '‘' | '’' | '\'') {
+                let prev = chars[j - 1];
+                let next = chars.get(j + 1).copied();
+                if prev.is_whitespace() {
+                    break;
+                } else if !next.is_some_and(char::is_alphanumeric) {
+                    return Mark::Apostrophe;
+                }
+            }
+        }
+    }
+    Mark::Close
+}
+
+fn is_elision(rest: &[char]) -> bool {
+    if rest.first().is_some_and(char::is_ascii_digit) {
+        // years, as in '90s
+        return true;
+    }
+    let word: String = rest
+        .iter()
+        .take_while(|c| c.is_alphabetic())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    ELISIONS.contains(&word.as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn swapped(s: &str) -> String {
+        let mut s = String::from(s);
+        swap(&mut s);
+        s
+    }
+
+    #[test]
+    fn it_swaps_speech() {
+        assert_eq!(swapped("‘Don’t,’ he said."), "“Don’t,” he said.");
+        assert_eq!(swapped("'Don't,' he said."), "\"Don't,\" he said.");
+    }
+
+    #[test]
+    fn it_swaps_nested_quotes() {
+        assert_eq!(
+            swapped("‘He told me “no” twice.’"),
+            "“He told me ‘no’ twice.”"
+        );
+    }
+
+    #[test]
+    fn it_leaves_apostrophes_alone() {
+        assert_eq!(swapped("’Tis the boys’ fault."), "’Tis the boys’ fault.");
+        assert_eq!(
+            swapped("‘tis true, in the ’90s."),
+            "‘tis true, in the ’90s."
+        );
+        assert_eq!(
+            swapped("‘The boys’ dogs barked,’ she said."),
+            "“The boys’ dogs barked,” she said."
+        );
+    }
+
+    #[test]
+    fn it_preserves_length() {
+        let s = "‘A “b” c’ 'd \"e\" f' g’s";
+        assert_eq!(swapped(s).len(), s.len());
+    }
+}
@@ -0,0 +1,313 @@
+//! Turns HTML that isn't well-formed XML into something `roxmltree` can parse.
+
+use std::{borrow::Cow, collections::HashSet};
+
+use crate::entities;
+
+/// Elements that never have content or an end tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+/// Elements that close an open `p`.
+const CLOSES_P: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "div",
+    "dl",
+    "fieldset",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+/// Elements that close an open element of the same name.
+const CLOSES_SELF: &[&str] = &["dd", "dt", "li", "option", "td", "th", "tr"];
+
+/// `data` as well-formed XML, repaired only if it has to be: first by
+/// replacing HTML's named entities and escaping stray `&`s, and failing that
+/// by rewriting it as balanced XHTML.
+pub fn well_formed(data: &str) -> Cow<'_, str> {
+    if roxmltree::Document::parse(data).is_ok() {
+        return Cow::Borrowed(data);
+    }
+    let escaped = escape_entities(data);
+    if roxmltree::Document::parse(&escaped).is_ok() {
+        return Cow::Owned(escaped);
+    }
+    Cow::Owned(rewrite(&escaped))
+}
+
+/// Replaces named entities that XML doesn't know with numeric ones, and
+/// escapes any `&` that doesn't start an entity.
+fn escape_entities(data: &str) -> String {
+    let mut out = String::with_capacity(data.len());
+    let mut rest = data;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        let terminated = rest[name_len..].starts_with(';');
+        let numeric = name
+            .strip_prefix('#')
+            .is_some_and(|n| match n.strip_prefix(['x', 'X']) {
+                Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                None => !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()),
+            });
+        let known = matches!(name, "amp" | "lt" | "gt" | "quot" | "apos");
+        if terminated && (numeric || known) {
+            out.push('&');
+            continue;
+        }
+        let text = match terminated {
+            true => entities::lookup(name),
+            false => entities::lookup_legacy(name),
+        };
+        match text {
+            Some(text) => {
+                for c in text.chars() {
+                    out.push_str(&format!("&#{};", u32::from(c)));
+                }
+                rest = &rest[name_len + usize::from(terminated)..];
+            }
+            None => out.push_str("&amp;"),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rewrites tag soup as XHTML: names are lowercased, attributes quoted and
+/// deduplicated, void elements closed, unclosed elements closed where HTML
+/// would close them, and stray end tags dropped.
+fn rewrite(data: &str) -> String {
+    // namespace prefixes that are never declared can't be parsed
+    let declared: HashSet<&str> = data
+        .match_indices("xmlns:")
+        .filter_map(|(i, _)| {
+            let rest = &data[i + 6..];
+            let end = rest.find(|c: char| !is_name_char(c))?;
+            Some(&rest[..end])
+        })
+        .chain(["xml", "xmlns"])
+        .collect();
+
+    let mut out = String::with_capacity(data.len() + data.len() / 8);
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = data;
+    while let Some(i) = rest.find('<') {
+        text(&mut out, &rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>").map_or(rest.len(), |end| end + 3);
+            out.push_str(&rest[..end]);
+            if end == rest.len() {
+                out.push_str("]]>");
+            }
+            rest = &rest[end..];
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            // doctypes and processing instructions aren't needed
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').unwrap_or(after.len());
+            let name = tag_name(&after[..end], &declared);
+            if let Some(depth) = open.iter().rposition(|n| *n == name) {
+                for name in open.drain(depth..).rev() {
+                    out.push_str(&format!("</{name}>"));
+                }
+            }
+            rest = after.get(end + 1..).unwrap_or("");
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            rest = start_tag(&mut out, &mut open, &rest[1..], &declared);
+        } else {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    text(&mut out, rest);
+    for name in open.drain(..).rev() {
+        out.push_str(&format!("</{name}>"));
+    }
+
+    match roxmltree::Document::parse(&out) {
+        Ok(_) => out,
+        // several elements or text at the top level
+        Err(_) => format!("<html>{out}</html>"),
+    }
+}
+
+/// Writes the start tag at the beginning of `rest`, which is just past its
+/// `<`, returning what follows it.
+fn start_tag<'a>(
+    out: &mut String,
+    open: &mut Vec<String>,
+    rest: &'a str,
+    declared: &HashSet<&str>,
+) -> &'a str {
+    let name_end = rest
+        .find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+        .unwrap_or(rest.len());
+    let name = tag_name(&rest[..name_end], declared);
+    let mut rest = &rest[name_end..];
+
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut self_closing = false;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            self_closing = after.starts_with('>');
+            rest = after;
+            continue;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len())
+            .max(1);
+        let attr = rest[..end].to_ascii_lowercase();
+        rest = rest[end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, after) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let inner = &after[1..];
+                        let end = inner.find(quote).unwrap_or(inner.len());
+                        (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = after;
+                value.to_owned()
+            }
+            // boolean attributes
+            None => attr.clone(),
+        };
+        let valid = attr.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && attr.chars().all(is_name_char)
+            && !matches!(attr.split_once(':'), Some((prefix, _)) if !declared.contains(prefix));
+        if valid && !attributes.iter().any(|(a, _)| *a == attr) {
+            attributes.push((attr, value));
+        }
+    }
+
+    if open.last().is_some_and(|top| {
+        (top == "p" && CLOSES_P.contains(&name.as_str()))
+            || (*top == name && CLOSES_SELF.contains(&name.as_str()))
+    }) {
+        out.push_str(&format!("</{}>", open.pop().unwrap()));
+    }
+
+    out.push('<');
+    out.push_str(&name);
+    for (attr, value) in &attributes {
+        out.push_str(&format!(" {attr}=\"{}\"", escape_attribute(value)));
+    }
+    if self_closing || VOID.contains(&name.as_str()) {
+        out.push_str("/>");
+        return rest;
+    }
+    out.push('>');
+
+    // the content of these isn't markup
+    if matches!(name.as_str(), "script" | "style") {
+        let close = format!("</{name}");
+        let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+        if name == "style" {
+            text(out, &rest[..end]);
+        }
+        out.push_str(&format!("</{name}>"));
+        rest = &rest[end..];
+        return rest.find('>').map_or("", |end| &rest[end + 1..]);
+    }
+    open.push(name);
+    rest
+}
+
+/// Lowercased name of a tag, without any undeclared namespace prefix.
+fn tag_name(raw: &str, declared: &HashSet<&str>) -> String {
+    let name = raw.trim().to_ascii_lowercase();
+    let name = match name.split_once(':') {
+        Some((prefix, local)) if !declared.contains(prefix) => local.to_owned(),
+        _ => name,
+    };
+    name.chars().filter(|&c| is_name_char(c)).collect()
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')
+}
+
+fn text(out: &mut String, s: &str) {
+    out.push_str(&s.replace('<', "&lt;"));
+}
+
+fn escape_attribute(s: &str) -> String {
+    s.replace('<', "&lt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_replaces_entities() {
+        assert_eq!(
+            escape_entities("A &mdash; B &amp; C &copy 2023 &#x41; & D &bogus;"),
+            "A &#8212; B &amp; C &#169; 2023 &#x41; &amp; D &amp;bogus;"
+        );
+    }
+
+    #[test]
+    fn it_repairs_tag_soup() {
+        let html = "<!DOCTYPE html><HTML><head><meta charset=utf-8><title>T</title></head>\
+            <body><p class=a class=b>One<br>two<p epub:type=x>Three &hellip; <b>4 < 5</body>";
+        let repaired = well_formed(html);
+        let xml = roxmltree::Document::parse(&repaired).unwrap();
+        let paragraphs: Vec<_> = xml
+            .descendants()
+            .filter(|n| n.has_tag_name("p"))
+            .map(|n| {
+                n.descendants()
+                    .filter(|n| n.is_text())
+                    .filter_map(|n| n.text())
+                    .collect::<String>()
+            })
+            .collect();
+        assert_eq!(paragraphs, ["Onetwo", "Three … 4 < 5"]);
+    }
+}
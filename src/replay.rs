@@ -0,0 +1,241 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context as _;
+
+use crate::{
+    backend::{ErrorMode, Len, Op},
+    book::Book,
+    config::Config,
+    epub::state_directory,
+    notes::Notes,
+    quotes::Quotes,
+    state::file_name,
+};
+
+/// Recorded edits of a typing session, which can be played back to see
+/// where the typist hesitated.
+///
+/// Logs are plain text: a header of `key value` lines describing the book and
+/// the options that affect the text, then a `chapter` line starting each
+/// chapter typed in, followed by one line per edit holding the milliseconds
+/// since the previous edit and `+<char>`, `-` (backspace) or `^` (delete
+/// word).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Log {
+    pub book: PathBuf,
+    pub identifier: String,
+    pub quotes: Quotes,
+    pub notes: Notes,
+    pub transliterate: bool,
+    pub errors: ErrorMode,
+    /// Fingerprint of the config's tables, empty in logs from older versions.
+    pub config: String,
+    pub segments: Vec<Segment>,
+}
+
+/// Edits made within one chapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub chapter: usize,
+    /// Cursor position typing started from.
+    pub start: Len,
+    /// Edits with their time since the chapter was entered.
+    pub ops: Vec<(Duration, Op)>,
+}
+
+impl Log {
+    pub fn new(
+        book: &dyn Book,
+        quotes: Quotes,
+        notes: Notes,
+        transliterate: bool,
+        errors: ErrorMode,
+        config: &Config,
+    ) -> Self {
+        Self {
+            book: book.path().to_owned(),
+            identifier: book.identifier().to_owned(),
+            quotes,
+            notes,
+            transliterate,
+            errors,
+            config: config.fingerprint(),
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        Self::parse(&data).with_context(|| format!("invalid log {}", path.display()))
+    }
+
+    /// Saves the log to a new file in the state directory, returning its path.
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let mut path = state_directory()?;
+        path.push("logs");
+        fs::create_dir_all(&path)?;
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        path.push(format!(
+            "{}-{}.log",
+            file_name(&self.identifier),
+            time.as_millis()
+        ));
+        fs::write(&path, self.serialize())?;
+        Ok(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.iter().all(|s| s.ops.is_empty())
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = format!(
+            "book {}\nidentifier {}\nquotes {}\nnotes {}\nerrors {}\nconfig {}\n",
+            self.book.display(),
+            self.identifier,
+            self.quotes.as_str(),
+            self.notes.as_str(),
+            self.errors.as_str(),
+            self.config,
+        );
+        if self.transliterate {
+            out.push_str("ascii\n");
+        }
+        for segment in &self.segments {
+            let start = segment.start;
+            writeln!(
+                out,
+                "chapter {} {} {}",
+                segment.chapter, start.bytes, start.chars
+            )
+            .unwrap();
+            let mut last = 0;
+            for &(time, op) in &segment.ops {
+                // deltas of whole milliseconds, without rounding errors adding up
+                let millis = time.as_millis();
+                write!(out, "{} ", millis.saturating_sub(last)).unwrap();
+                last = millis;
+                match op {
+                    Op::Push('\n') => out.push_str("+\\n"),
+                    Op::Push('\\') => out.push_str("+\\\\"),
+                    Op::Push(c) => write!(out, "+{c}").unwrap(),
+                    Op::Pop => out.push('-'),
+                    Op::DeleteWord => out.push('^'),
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    fn parse(data: &str) -> anyhow::Result<Self> {
+        let mut log = Self {
+            book: PathBuf::new(),
+            identifier: String::new(),
+            quotes: Quotes::default(),
+            notes: Notes::default(),
+            transliterate: false,
+            errors: ErrorMode::default(),
+            config: String::new(),
+            segments: Vec::new(),
+        };
+        let mut last = Duration::ZERO;
+        for (i, line) in data.lines().enumerate() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let invalid = || format!("line {}: invalid `{key}` entry", i + 1);
+            match key {
+                "book" => log.book = PathBuf::from(value),
+                "identifier" => log.identifier = value.to_owned(),
+                "quotes" => log.quotes = value.parse().with_context(invalid)?,
+                "notes" => log.notes = value.parse().with_context(invalid)?,
+                "errors" => log.errors = value.parse().with_context(invalid)?,
+                "config" => log.config = value.to_owned(),
+                "ascii" => log.transliterate = true,
+                "chapter" => {
+                    let numbers = value
+                        .split(' ')
+                        .map(str::parse)
+                        .collect::<Result<Vec<usize>, _>>()
+                        .with_context(invalid)?;
+                    let &[chapter, bytes, chars] = numbers.as_slice() else {
+                        anyhow::bail!(invalid());
+                    };
+                    log.segments.push(Segment {
+                        chapter,
+                        start: Len::new(bytes, chars),
+                        ops: Vec::new(),
+                    });
+                    last = Duration::ZERO;
+                }
+                _ if key.bytes().all(|b| b.is_ascii_digit()) && !key.is_empty() => {
+                    let segment = log.segments.last_mut().with_context(invalid)?;
+                    last += Duration::from_millis(key.parse().with_context(invalid)?);
+                    let op = match value {
+                        "+\\n" => Op::Push('\n'),
+                        "+\\\\" => Op::Push('\\'),
+                        "-" => Op::Pop,
+                        "^" => Op::DeleteWord,
+                        _ => {
+                            let mut chars = value.chars();
+                            match (chars.next(), chars.next(), chars.next()) {
+                                (Some('+'), Some(c), None) => Op::Push(c),
+                                _ => anyhow::bail!(invalid()),
+                            }
+                        }
+                    };
+                    segment.ops.push((last, op));
+                }
+                // entries from newer versions
+                _ => {}
+            }
+        }
+        anyhow::ensure!(!log.identifier.is_empty(), "missing book identifier");
+        anyhow::ensure!(!log.segments.is_empty(), "no chapters recorded");
+        Ok(log)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_roundtrips() {
+        let ms = Duration::from_millis;
+        let log = Log {
+            book: PathBuf::from("/books/a book.epub"),
+            identifier: String::from("urn:isbn:123"),
+            quotes: Quotes::Swap,
+            notes: Notes::Panel,
+            transliterate: true,
+            errors: ErrorMode::Stop,
+            config: String::from("config:0123456789abcdef"),
+            segments: vec![
+                Segment {
+                    chapter: 2,
+                    start: Len::new(10, 9),
+                    ops: vec![
+                        (ms(1500), Op::Push(' ')),
+                        (ms(1620), Op::Push('\n')),
+                        (ms(1700), Op::Pop),
+                        (ms(2400), Op::DeleteWord),
+                        (ms(2401), Op::Push('\\')),
+                    ],
+                },
+                Segment {
+                    chapter: 3,
+                    start: Len::default(),
+                    ops: vec![(ms(300), Op::Push('é'))],
+                },
+            ],
+        };
+        assert_eq!(Log::parse(&log.serialize()).unwrap(), log);
+    }
+}
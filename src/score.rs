@@ -0,0 +1,185 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::backend::{Keystroke, Len};
+
+/// Characters per "word" when computing words per minute.
+pub(crate) const WORD_LEN: f64 = 5.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct Score {
+    pub elapsed: Duration,
+    pub keystrokes: usize,
+    pub errors: usize,
+    pub uncorrected: usize,
+    missed: HashMap<char, usize>,
+    words: HashMap<String, usize>,
+}
+
+impl Score {
+    /// `uncorrected` is the number of errors still present in the typed text;
+    /// every mistyped keystroke in `keystrokes` counts towards accuracy,
+    /// whether it was later corrected or not.
+    pub fn new(text: &str, keystrokes: &[Keystroke], uncorrected: usize) -> Self {
+        let mut score = Self {
+            elapsed: keystrokes.last().map_or(Duration::ZERO, |k| k.time)
+                - keystrokes.first().map_or(Duration::ZERO, |k| k.time),
+            keystrokes: keystrokes.len(),
+            uncorrected,
+            ..Self::default()
+        };
+        for k in keystrokes.iter().filter(|k| !k.correct) {
+            score.errors += 1;
+            *score.missed.entry(k.expected).or_default() += 1;
+            if let Some(word) = word_at(text, k.position) {
+                *score.words.entry(word).or_default() += 1;
+            }
+        }
+        score
+    }
+
+    /// A score of just its totals, without the characters and words missed.
+    pub fn from_totals(
+        elapsed: Duration,
+        keystrokes: usize,
+        errors: usize,
+        uncorrected: usize,
+    ) -> Self {
+        Self {
+            elapsed,
+            keystrokes,
+            errors,
+            uncorrected,
+            ..Self::default()
+        }
+    }
+
+    /// Adds the keystrokes of another score, e.g. one of a following chapter.
+    pub fn combine(&mut self, other: Score) {
+        self.elapsed += other.elapsed;
+        self.keystrokes += other.keystrokes;
+        self.errors += other.errors;
+        self.uncorrected += other.uncorrected;
+        for (c, n) in other.missed {
+            *self.missed.entry(c).or_default() += n;
+        }
+        for (word, n) in other.words {
+            *self.words.entry(word).or_default() += n;
+        }
+    }
+
+    pub fn gross_wpm(&self) -> f64 {
+        self.per_minute(self.keystrokes as f64 / WORD_LEN)
+    }
+
+    pub fn net_wpm(&self) -> f64 {
+        self.per_minute(self.keystrokes as f64 / WORD_LEN - self.uncorrected as f64)
+            .max(0.0)
+    }
+
+    /// Fraction of keystrokes that were correct, in `0.0..=1.0`.
+    pub fn accuracy(&self) -> f64 {
+        match self.keystrokes {
+            0 => 1.0,
+            n => (n - self.errors) as f64 / n as f64,
+        }
+    }
+
+    pub fn most_missed(&self, n: usize) -> Vec<(char, usize)> {
+        top(&self.missed, n)
+    }
+
+    pub fn worst_words(&self, n: usize) -> Vec<(String, usize)> {
+        top(&self.words, n)
+    }
+
+    fn per_minute(&self, amount: f64) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => amount * 60.0 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+fn top<K: Clone + Ord>(counts: &HashMap<K, usize>, n: usize) -> Vec<(K, usize)> {
+    let mut sorted: Vec<_> = counts.iter().map(|(k, &v)| (k.clone(), v)).collect();
+    sorted.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted.truncate(n);
+    sorted
+}
+
+/// The whitespace-delimited word containing `position`, without surrounding
+/// punctuation and lowercased so that occurrences can be grouped.
+fn word_at(text: &str, position: Len) -> Option<String> {
+    let start = text[..position.bytes]
+        .rfind(char::is_whitespace)
+        .map_or(0, |i| i + 1);
+    let end = text[position.bytes..]
+        .find(char::is_whitespace)
+        .map_or(text.len(), |i| position.bytes + i);
+    let word = text[start..end].trim_matches(|c: char| !c.is_alphanumeric());
+    (!word.is_empty()).then(|| word.to_lowercase())
+}
+
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keystroke(text: &str, chars: usize, typed: char, millis: u64) -> Keystroke {
+        let bytes = text.char_indices().nth(chars).unwrap().0;
+        Keystroke {
+            time: Duration::from_millis(millis),
+            position: Len::new(bytes, chars),
+            expected: text[bytes..].chars().next().unwrap(),
+            typed,
+            correct: text[bytes..].starts_with(typed),
+        }
+    }
+
+    #[test]
+    fn it_computes_speed_and_accuracy() {
+        let text = "the cat sat";
+        let keystrokes: Vec<_> = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| keystroke(text, i, if i == 5 { 'x' } else { c }, i as u64 * 1000))
+            .collect();
+        let score = Score::new(text, &keystrokes, 1);
+
+        assert_eq!(score.elapsed, Duration::from_secs(10));
+        assert_eq!(score.errors, 1);
+        assert!((score.gross_wpm() - 13.2).abs() < 1e-9);
+        assert!((score.net_wpm() - 7.2).abs() < 1e-9);
+        assert!((score.accuracy() - 10.0 / 11.0).abs() < 1e-9);
+        assert_eq!(score.most_missed(3), vec![('a', 1)]);
+        assert_eq!(score.worst_words(3), vec![(String::from("cat"), 1)]);
+    }
+
+    #[test]
+    fn it_combines_scores() {
+        let text = "ab";
+        let mut score = Score::new(text, &[keystroke(text, 0, 'x', 0)], 1);
+        score.combine(Score::new(
+            text,
+            &[keystroke(text, 0, 'x', 0), keystroke(text, 1, 'b', 2000)],
+            1,
+        ));
+        assert_eq!(score.elapsed, Duration::from_secs(2));
+        assert_eq!(
+            (score.keystrokes, score.errors, score.uncorrected),
+            (3, 2, 2)
+        );
+        assert_eq!(score.most_missed(3), vec![('a', 2)]);
+    }
+
+    #[test]
+    fn it_strips_punctuation_from_words() {
+        let text = "\"Hello,\" she said.";
+        assert_eq!(word_at(text, Len::new(3, 3)), Some(String::from("hello")));
+        assert_eq!(word_at(text, Len::new(17, 17)), Some(String::from("said")));
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::{backend::Len, epub::state_directory, quotes::Quotes};
+
+/// Per-book state that survives between sessions, keyed by the book's
+/// identifier.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookState {
+    pub progress: Option<Progress>,
+    pub quotes: Option<Quotes>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub chapter: usize,
+    pub cursor: Len,
+}
+
+impl BookState {
+    pub fn load(identifier: &str) -> anyhow::Result<Option<Self>> {
+        match fs::read_to_string(Self::path(identifier)?) {
+            Ok(data) => Ok(Some(Self::parse(&data))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, identifier: &str) -> anyhow::Result<()> {
+        let path = Self::path(identifier)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    fn path(identifier: &str) -> anyhow::Result<PathBuf> {
+        let mut path = state_directory()?;
+        path.push("books");
+        path.push(file_name(identifier));
+        Ok(path)
+    }
+
+    // unknown or malformed entries are skipped so that older/newer state files
+    // still load
+    fn parse(data: &str) -> Self {
+        let mut state = Self::default();
+        for line in data.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            if key == "quotes" {
+                state.quotes = value.trim().parse().ok().or(state.quotes);
+                continue;
+            }
+            let mut numbers = value.split_whitespace().map(str::parse::<usize>);
+            match (key, numbers.next(), numbers.next()) {
+                ("chapter", Some(Ok(chapter)), _) => {
+                    state.progress.get_or_insert_with(Progress::default).chapter = chapter
+                }
+                ("cursor", Some(Ok(bytes)), Some(Ok(chars))) => {
+                    state.progress.get_or_insert_with(Progress::default).cursor =
+                        Len::new(bytes, chars)
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+
+    fn serialize(&self) -> String {
+        let mut data = String::new();
+        if let Some(progress) = self.progress {
+            data += &format!(
+                "chapter {}\ncursor {} {}\n",
+                progress.chapter, progress.cursor.bytes, progress.cursor.chars
+            );
+        }
+        if let Some(quotes) = self.quotes {
+            data += &format!("quotes {}\n", quotes.as_str());
+        }
+        data
+    }
+}
+
+pub(crate) fn file_name(identifier: &str) -> String {
+    identifier
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_roundtrips() {
+        let state = BookState {
+            progress: Some(Progress {
+                chapter: 3,
+                cursor: Len::new(120, 117),
+            }),
+            quotes: Some(Quotes::Swap),
+        };
+        assert_eq!(BookState::parse(&state.serialize()), state);
+        assert_eq!(
+            BookState::parse(&BookState::default().serialize()),
+            BookState::default()
+        );
+    }
+
+    #[test]
+    fn it_sanitises_identifiers() {
+        assert_eq!(
+            file_name("urn:uuid:1b2c/3d4e"),
+            String::from("urn_uuid_1b2c_3d4e")
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::{collections::BTreeMap, fmt::Write as _, fs, io, path::PathBuf, time::Duration};
+
+use anyhow::Context as _;
+
+use crate::{backend::Keystroke, epub::state_directory};
+
+/// Pauses longer than this aren't counted as the time taken to type a key.
+const MAX_LATENCY: Duration = Duration::from_secs(2);
+/// Time an error is considered to cost when ranking keys, in milliseconds.
+const ERROR_PENALTY: f64 = 1000.0;
+/// Keys typed fewer times than this aren't ranked.
+const MIN_SAMPLES: u64 = 10;
+
+/// Counts for a character or bigram, across every session.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct KeyStats {
+    pub typed: u64,
+    pub errors: u64,
+    /// Keystrokes typed straight after the previous one, which have a latency.
+    pub timed: u64,
+    /// Total latency of `timed` keystrokes, in milliseconds.
+    pub latency: u64,
+}
+
+impl KeyStats {
+    pub fn error_rate(&self) -> f64 {
+        match self.typed {
+            0 => 0.0,
+            n => self.errors as f64 / n as f64,
+        }
+    }
+
+    /// Mean time taken to type the key, in milliseconds.
+    pub fn mean_latency(&self) -> Option<f64> {
+        match self.timed {
+            0 => None,
+            n => Some(self.latency as f64 / n as f64),
+        }
+    }
+
+    /// How much the key slows typing down, counting errors as a fixed delay.
+    /// `None` until it has been typed enough to tell.
+    pub fn weakness(&self) -> Option<f64> {
+        if self.typed < MIN_SAMPLES {
+            return None;
+        }
+        Some(self.mean_latency().unwrap_or(0.0) + self.error_rate() * ERROR_PENALTY)
+    }
+
+    fn add(&mut self, correct: bool, latency: Option<Duration>) {
+        self.typed += 1;
+        self.errors += u64::from(!correct);
+        if let Some(latency) = latency {
+            self.timed += 1;
+            self.latency += latency.as_millis() as u64;
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.typed += other.typed;
+        self.errors += other.errors;
+        self.timed += other.timed;
+        self.latency += other.latency;
+    }
+}
+
+/// Per-character and per-bigram statistics, keyed by the expected text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub chars: BTreeMap<char, KeyStats>,
+    pub bigrams: BTreeMap<(char, char), KeyStats>,
+}
+
+impl Stats {
+    pub fn from_keystrokes(keystrokes: &[Keystroke]) -> Self {
+        let mut stats = Self::default();
+        let mut previous: Option<&Keystroke> = None;
+        for k in keystrokes {
+            // only keys typed one after the other, without corrections in
+            // between, have a latency and form a bigram
+            let prev = previous
+                .filter(|p| p.position.chars + 1 == k.position.chars)
+                .filter(|p| k.time - p.time <= MAX_LATENCY);
+            let latency = prev.map(|p| k.time - p.time);
+            stats
+                .chars
+                .entry(k.expected)
+                .or_default()
+                .add(k.correct, latency);
+            if let Some(p) = prev {
+                stats
+                    .bigrams
+                    .entry((p.expected, k.expected))
+                    .or_default()
+                    .add(k.correct, latency);
+            }
+            previous = Some(k);
+        }
+        stats
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for (c, s) in &other.chars {
+            self.chars.entry(*c).or_default().merge(s);
+        }
+        for (b, s) in &other.bigrams {
+            self.bigrams.entry(*b).or_default().merge(s);
+        }
+    }
+
+    /// Characters ranked from weakest, along with their weakness.
+    pub fn weakest_chars(&self) -> Vec<(char, f64)> {
+        weakest(&self.chars)
+    }
+
+    /// Bigrams ranked from weakest, along with their weakness.
+    pub fn weakest_bigrams(&self) -> Vec<((char, char), f64)> {
+        weakest(&self.bigrams)
+    }
+
+    /// Table of the `count` weakest characters and bigrams.
+    pub fn report(&self, count: usize) -> String {
+        let mut report = String::new();
+        let row = |report: &mut String, key: String, s: &KeyStats| {
+            let latency = match s.mean_latency() {
+                Some(ms) => format!("{ms:.0}ms"),
+                None => String::from("-"),
+            };
+            writeln!(
+                report,
+                "  {key:<8}{:>8}{:>7.1}%{latency:>9}",
+                s.typed,
+                s.error_rate() * 100.0,
+            )
+            .unwrap();
+        };
+        let header = "typed  errors  latency";
+        writeln!(report, "{:<10}{header:>25}", "keys").unwrap();
+        for (c, _) in self.weakest_chars().into_iter().take(count) {
+            row(&mut report, visible(c).to_string(), &self.chars[&c]);
+        }
+        writeln!(report, "\n{:<10}{header:>25}", "bigrams").unwrap();
+        for ((a, b), _) in self.weakest_bigrams().into_iter().take(count) {
+            let key = format!("{}{}", visible(a), visible(b));
+            row(&mut report, key, &self.bigrams[&(a, b)]);
+        }
+        report
+    }
+
+    pub fn load() -> anyhow::Result<Self> {
+        let path = stats_path()?;
+        match fs::read_to_string(&path) {
+            Ok(data) => Self::parse(&data).with_context(|| format!("in {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Adds `other` to the saved statistics.
+    pub fn save_merged(other: &Self) -> anyhow::Result<()> {
+        let mut stats = Self::load()?;
+        stats.merge(other);
+        let path = stats_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, stats.serialize())?;
+        Ok(())
+    }
+
+    fn serialize(&self) -> String {
+        // characters are written as code points so whitespace survives
+        let mut data = String::new();
+        let line = |data: &mut String, key: String, s: &KeyStats| {
+            writeln!(
+                data,
+                "{key} {} {} {} {}",
+                s.typed, s.errors, s.timed, s.latency
+            )
+            .unwrap();
+        };
+        for (c, s) in &self.chars {
+            line(&mut data, format!("char {:x}", *c as u32), s);
+        }
+        for ((a, b), s) in &self.bigrams {
+            line(
+                &mut data,
+                format!("bigram {:x} {:x}", *a as u32, *b as u32),
+                s,
+            );
+        }
+        data
+    }
+
+    fn parse(data: &str) -> anyhow::Result<Self> {
+        let mut stats = Self::default();
+        for (i, line) in data.lines().enumerate() {
+            stats
+                .parse_line(line)
+                .with_context(|| format!("line {}", i + 1))?;
+        }
+        Ok(stats)
+    }
+
+    fn parse_line(&mut self, line: &str) -> anyhow::Result<()> {
+        fn code_point(s: Option<&str>) -> anyhow::Result<char> {
+            let s = s.context("missing character")?;
+            char::from_u32(u32::from_str_radix(s, 16)?).context("invalid character")
+        }
+
+        let mut words = line.split_ascii_whitespace();
+        match words.next() {
+            Some("char") => {
+                let c = code_point(words.next())?;
+                self.chars.insert(c, parse_counts(words)?);
+            }
+            Some("bigram") => {
+                let bigram = (code_point(words.next())?, code_point(words.next())?);
+                self.bigrams.insert(bigram, parse_counts(words)?);
+            }
+            Some(entry) => anyhow::bail!("unknown entry `{entry}`"),
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_counts<'a>(mut words: impl Iterator<Item = &'a str>) -> anyhow::Result<KeyStats> {
+    let mut next =
+        || -> anyhow::Result<u64> { Ok(words.next().context("missing count")?.parse()?) };
+    Ok(KeyStats {
+        typed: next()?,
+        errors: next()?,
+        timed: next()?,
+        latency: next()?,
+    })
+}
+
+fn weakest<K: Copy>(stats: &BTreeMap<K, KeyStats>) -> Vec<(K, f64)> {
+    let mut ranked: Vec<_> = stats
+        .iter()
+        .filter_map(|(&k, s)| Some((k, s.weakness()?)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+fn visible(c: char) -> char {
+    match c {
+        ' ' => '␣',
+        '\n' => '↵',
+        c => c,
+    }
+}
+
+fn stats_path() -> anyhow::Result<PathBuf> {
+    let mut path = state_directory()?;
+    path.push("stats");
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::Len;
+
+    #[test]
+    fn it_counts_chars_and_bigrams() {
+        let keystroke = |chars, expected, correct, millis| Keystroke {
+            time: Duration::from_millis(millis),
+            position: Len::new(chars, chars),
+            expected,
+            typed: expected,
+            correct,
+        };
+        let stats = Stats::from_keystrokes(&[
+            keystroke(0, 't', true, 0),
+            keystroke(1, 'h', false, 200),
+            keystroke(1, 'h', true, 900),
+            keystroke(2, 'e', true, 1000),
+        ]);
+        assert_eq!(stats.chars[&'h'].typed, 2);
+        assert_eq!(stats.chars[&'h'].errors, 1);
+        assert_eq!(stats.chars[&'t'].timed, 0);
+        let th = stats.bigrams[&('t', 'h')];
+        assert_eq!((th.typed, th.timed, th.latency), (1, 1, 200));
+        assert_eq!(stats.bigrams[&('h', 'e')].latency, 100);
+        assert_eq!(Stats::parse(&stats.serialize()).unwrap(), stats);
+    }
+}
@@ -1,10 +1,18 @@
 use std::marker::PhantomData;
 
 bitflags::bitflags! {
-    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-    pub struct Style: u8 {
-        const ITALIC = 0b1;
-        const BOLD   = 0b10;
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Style: u16 {
+        const ITALIC        = 0b1;
+        const BOLD          = 0b10;
+        const UNDERLINE     = 0b100;
+        const STRIKETHROUGH = 0b1000;
+        /// Not shown, since terminals have no small capitals.
+        const SMALL_CAPS    = 0b10000;
+        const SUPERSCRIPT   = 0b100000;
+        const SUBSCRIPT     = 0b1000000;
+        const MONOSPACE     = 0b10000000;
+        const SMALL         = 0b100000000;
     }
 }
 
@@ -113,22 +121,21 @@ where
 impl<T: Ord + Copy> Styling<T> {
     pub fn iter(&self, start: T, end: T) -> StylingIter<'_, T> {
         assert!(end >= start);
+        // a range that ends soonest needn't start soonest, so the style at
+        // `start` is made up of every range that's open there
+        let style = self
+            .starts
+            .iter()
+            .take_while(|s| s.range.start <= start)
+            .filter(|s| s.range.end > start)
+            .fold(Style::empty(), |style, s| style | s.style);
+        let start_idx = self.starts.partition_point(|s| s.range.start <= start);
         let end_idx = self.ends.partition_point(|s| s.range.end <= start);
-        let start_idx = self
-            .ends
-            .get(end_idx)
-            .map(|e| (e.range.start, e.style))
-            .and_then(|e| {
-                self.starts
-                    .binary_search_by_key(&e, |s| (s.range.start, s.style))
-                    .ok()
-            })
-            .unwrap_or(end_idx);
 
         StylingIter {
             starts: self.starts[start_idx..].iter().peekable(),
             ends: self.ends[end_idx..].iter().peekable(),
-            style: Style::empty(),
+            style,
             idx: start,
             end,
             ended: start == end,
@@ -281,6 +288,26 @@ mod test {
         );
     }
 
+    #[test]
+    fn it_keeps_styles_that_enclose_the_start() {
+        let styles = Styling::builder()
+            .add(Style::ITALIC, 0..10)
+            .add(Style::MONOSPACE, 2..4)
+            .add(Style::SUPERSCRIPT | Style::SMALL, 6..8)
+            .build();
+
+        let res: Vec<_> = styles.iter(3, 9).collect();
+        assert_eq!(
+            &res[..],
+            &[
+                (Style::ITALIC | Style::MONOSPACE, 1),
+                (Style::ITALIC, 2),
+                (Style::ITALIC | Style::SUPERSCRIPT | Style::SMALL, 2),
+                (Style::ITALIC, 1),
+            ]
+        );
+    }
+
     #[test]
     fn it_decomposes_style_flags() {
         let styles = Styling::builder()
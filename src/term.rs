@@ -1,25 +1,46 @@
 use std::{
+    borrow::Cow,
+    cell::Cell,
     cmp::Ordering,
     io::Write,
-    ops::{Bound, RangeBounds},
+    ops::{Bound, Range, RangeBounds},
+    path::PathBuf,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crossterm::{
     cursor,
     event::{KeyCode, KeyEvent, KeyModifiers},
     queue,
-    style::{Attribute, Color, ResetColor, SetAttribute, SetForegroundColor},
+    style::{Attribute, Color, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
     terminal,
 };
 
 use crate::{
-    backend::{Backend, Len},
-    epub::Epub,
+    backend::{Backend, BlockKind, ErrorMode, Len},
+    book::Book,
+    config::Config,
+    epub::Align,
+    ghost::{Ghost, Pace, Run},
+    graphics::{Picture, Protocol, KITTY_CLEAR},
+    history::Entry,
+    notes::Notes,
+    quotes::Quotes,
+    replay::{Log, Segment},
+    score::{format_duration, Score},
+    state::{BookState, Progress},
+    stats::Stats,
     style::Style,
 };
 
 const PARAGRAPH_TERMINATOR: &str = "↵";
+const ANNOTATION_WIDTH: u16 = 11;
+/// Narrowest the panel notes are shown in can be.
+const MIN_NOTES_WIDTH: u16 = 20;
+const QUOTE_GUTTER: &str = "│";
+/// Columns block quotes are indented by, including the gutter.
+const QUOTE_INDENT: u16 = 2;
 // const PARAGRAPH_TERMINATOR: &str = "¬";
 // const PARAGRAPH_TERMINATOR: &str = " ";
 
@@ -37,6 +58,21 @@ struct VirtualLine {
     end: Len,
     separator_len: Len,
     linebreak: Linebreak,
+    kind: BlockKind,
+    /// Columns the line is indented by within the content, to align it or
+    /// make room for a quote's gutter.
+    offset: u16,
+}
+
+struct ImagePlacement {
+    /// Index into the backend's images.
+    image: usize,
+    /// Virtual line of the image's top row.
+    line: usize,
+    rows: u16,
+    /// `None` if the image is shown as a placeholder.
+    picture: Option<Picture>,
+    transmitted: Cell<bool>,
 }
 
 #[derive(Debug)]
@@ -57,7 +93,8 @@ impl ScreenLine<'_> {
 
 enum State {
     ChapterSelect,
-    Chapter(ChapterDisplay),
+    Chapter(Box<ChapterDisplay>),
+    Score(ScoreDisplay),
 }
 
 struct Dimensions {
@@ -66,26 +103,160 @@ struct Dimensions {
     width: u16,
 }
 
+impl Dimensions {
+    fn new(width: u16, view_width: u16, view_height: u16) -> Self {
+        let width = width.min(view_width);
+        Self {
+            screen_size: (view_width, view_height),
+            anchor: (view_width / 2 - width / 2, view_height / 2),
+            width,
+        }
+    }
+}
+
+/// Options given on the command line.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Width requested by the user, which may be more than currently fits.
+    pub width: u16,
+    pub graphics: Protocol,
+    /// Overrides and replaces the option saved for the book.
+    pub quotes: Option<Quotes>,
+    pub notes: Notes,
+    /// Accept ASCII transliterations of characters missing from the keyboard.
+    pub transliterate: bool,
+    pub errors: ErrorMode,
+    pub goal: Goal,
+    /// Record a log of every session for replaying later.
+    pub record: bool,
+    /// Race a ghost cursor typing at this pace.
+    pub pace: Option<Pace>,
+}
+
+/// What ends a typing session.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Goal {
+    /// The end of the chapter.
+    #[default]
+    Chapter,
+    /// Typing for a while, carrying on into following chapters.
+    Time(Duration),
+    /// Typing a number of words, carrying on into following chapters.
+    Words(usize),
+}
+
+/// A session with a time or word goal, which can span several chapters.
+#[derive(Default)]
+struct Session {
+    /// Chapter the session started in.
+    chapter: usize,
+    /// Score of the chapters finished so far.
+    score: Option<Score>,
+    elapsed: Duration,
+    words: usize,
+    /// Where typing started in the current chapter.
+    start: Len,
+}
+
+/// Playback of a recorded session.
+struct Replay {
+    segments: Vec<Segment>,
+    segment: usize,
+    op: usize,
+    speed: f64,
+    /// When the current segment's chapter was entered.
+    started: Instant,
+    /// Score of the segments played so far.
+    score: Option<Score>,
+}
+
 pub struct Display {
     dimensions: Arc<Dimensions>,
-    book: Epub,
+    options: Options,
+    book: Box<dyn Book>,
     chapter: usize,
     state: State,
+    saved: BookState,
+    config: Arc<Config>,
+    session: Option<Session>,
+    recording: Option<Log>,
+    /// Paths of the logs recorded so far.
+    logs: Vec<PathBuf>,
+    replay: Option<Replay>,
+    /// Text of a drill being typed instead of the book.
+    drill: Option<String>,
+    /// Size of each chapter, for showing progress through the book.
+    chapter_sizes: Vec<u64>,
+    /// Shown in the status line of the chapter select, e.g. why a chapter
+    /// couldn't be opened.
+    message: Option<String>,
 }
 
 impl Display {
-    pub fn new(book: Epub, width: u16, view_width: u16, view_height: u16) -> Self {
-        let width = width.min(view_width);
-
+    pub fn new(
+        mut book: Box<dyn Book>,
+        options: Options,
+        config: Config,
+        view_width: u16,
+        view_height: u16,
+    ) -> Self {
         Self {
             state: State::ChapterSelect,
+            chapter_sizes: book.chapter_sizes(),
             book,
             chapter: 0,
-            dimensions: Arc::new(Dimensions {
-                screen_size: (view_width, view_height),
-                anchor: (view_width / 2 - width / 2, view_height / 2),
-                width,
-            }),
+            saved: BookState::default(),
+            config: Arc::new(config),
+            session: None,
+            recording: None,
+            logs: Vec::new(),
+            replay: None,
+            drill: None,
+            message: None,
+            dimensions: Arc::new(Dimensions::new(options.width, view_width, view_height)),
+            options,
+        }
+    }
+
+    /// Plays back a recorded session instead of typing, with the options it
+    /// was recorded with. Must be called before `enter`.
+    pub fn play(&mut self, log: Log, speed: f64) {
+        self.options.quotes = Some(log.quotes);
+        self.options.notes = log.notes;
+        self.options.transliterate = log.transliterate;
+        self.options.errors = log.errors;
+        self.options.goal = Goal::Chapter;
+        self.options.record = false;
+        self.options.pace = None;
+        self.replay = Some(Replay {
+            segments: log.segments,
+            segment: 0,
+            op: 0,
+            speed,
+            started: Instant::now(),
+            score: None,
+        });
+    }
+
+    /// Types a drill instead of the book. Must be called before `enter`.
+    pub fn drill(&mut self, text: String) {
+        self.options.goal = Goal::Chapter;
+        self.options.record = false;
+        self.options.pace = None;
+        self.drill = Some(text);
+    }
+
+    /// Paths of the session logs saved while recording.
+    pub fn recorded_logs(&self) -> &[PathBuf] {
+        &self.logs
+    }
+
+    pub fn resize(&mut self, view_width: u16, view_height: u16) {
+        self.dimensions = Arc::new(Dimensions::new(self.options.width, view_width, view_height));
+        match &mut self.state {
+            State::ChapterSelect => {}
+            State::Chapter(display) => display.resize(Arc::clone(&self.dimensions)),
+            State::Score(display) => display.dimensions = Arc::clone(&self.dimensions),
         }
     }
 
@@ -97,16 +268,404 @@ impl Display {
             let _ = Self::cleanup(&mut std::io::stdout());
             hook(info);
         }));
+
+        if self.replay.is_some() {
+            self.saved.quotes = self.options.quotes;
+            self.start_segment()?;
+            return self.full_render(w);
+        }
+        if let Some(text) = &self.drill {
+            let backend = Backend::from_text(
+                text.clone(),
+                Arc::clone(&self.config),
+                self.options.transliterate,
+                self.options.errors,
+            );
+            let mut display =
+                ChapterDisplay::new(Arc::clone(&self.dimensions), backend, &self.options);
+            display.heading = String::from("Drill");
+            self.state = State::Chapter(Box::new(display));
+            return self.full_render(w);
+        }
+
+        self.saved = BookState::load(self.book.identifier())?.unwrap_or_default();
+        if let Some(quotes) = self
+            .options
+            .quotes
+            .filter(|&q| self.saved.quotes != Some(q))
+        {
+            self.saved.quotes = Some(quotes);
+            self.save_state()?;
+        }
+        let progress = self.saved.progress;
+        if let Some(progress) = progress.filter(|p| p.chapter < self.book.chapter_count()) {
+            self.chapter = progress.chapter;
+            self.try_enter_chapter()?;
+        }
+
         self.full_render(w)?;
         Ok(())
     }
 
-    pub fn exit(&self, w: &mut impl Write) -> anyhow::Result<()> {
+    pub fn exit(&mut self, w: &mut impl Write) -> anyhow::Result<()> {
         Self::cleanup(w)?;
         let _ = std::panic::take_hook();
+        self.save_progress()?;
+        self.end_recording()
+    }
+
+    /// Enters the selected chapter, or goes back to the chapter select with
+    /// the reason in the status line if the chapter can't be read.
+    fn try_enter_chapter(&mut self) -> anyhow::Result<()> {
+        if let Err(e) = self.enter_chapter() {
+            self.end_recording()?;
+            self.session = None;
+            self.state = State::ChapterSelect;
+            self.message = Some(format!("couldn't open chapter: {e:#}"));
+        }
+        Ok(())
+    }
+
+    fn enter_chapter(&mut self) -> anyhow::Result<()> {
+        let mut display = ChapterDisplay::enter(
+            Arc::clone(&self.dimensions),
+            &mut *self.book,
+            self.chapter,
+            &self.options,
+            self.saved.quotes.unwrap_or_default(),
+            Arc::clone(&self.config),
+        )?;
+        if let Some(progress) = self.saved.progress.filter(|p| p.chapter == self.chapter) {
+            display.backend.resume_at(progress.cursor);
+        }
+        display.start = display.backend.cursor();
+        display.heading = format!(
+            "{} · {}",
+            self.book.title(),
+            self.book.chapters()[self.chapter].name()
+        );
+        let total = self.chapter_sizes.iter().sum::<u64>().max(1) as f64;
+        let before = self.chapter_sizes[..self.chapter].iter().sum::<u64>() as f64;
+        display.book_share =
+            Some(before / total..(before + self.chapter_sizes[self.chapter] as f64) / total);
+        display.ghost = match self.options.pace {
+            Some(Pace::Best) => Run::load(self.book.identifier(), self.chapter)
+                .ok()
+                .flatten()
+                // runs that stopped short, e.g. at text that isn't typed
+                .filter(|run| run.chars() == display.backend.text().chars().count())
+                .map(Ghost::Run),
+            Some(Pace::Wpm(wpm)) => Some(Ghost::from_wpm(wpm)),
+            None => None,
+        };
+        if self.options.goal != Goal::Chapter {
+            let chapter = self.chapter;
+            self.session
+                .get_or_insert_with(|| Session {
+                    chapter,
+                    ..Session::default()
+                })
+                .start = display.backend.cursor();
+        }
+        if self.options.record {
+            let recording = self.recording.get_or_insert_with(|| {
+                Log::new(
+                    &*self.book,
+                    self.saved.quotes.unwrap_or_default(),
+                    self.options.notes,
+                    self.options.transliterate,
+                    self.options.errors,
+                    &self.config,
+                )
+            });
+            recording.segments.push(Segment {
+                chapter: self.chapter,
+                start: display.backend.cursor(),
+                ops: Vec::new(),
+            });
+            display.backend.record();
+        }
+        self.state = State::Chapter(Box::new(display));
+        Ok(())
+    }
+
+    /// Moves the edits recorded in the current chapter into the log.
+    fn collect_log(&mut self) {
+        if let (Some(recording), State::Chapter(display)) = (&mut self.recording, &mut self.state) {
+            if let Some(segment) = recording.segments.last_mut() {
+                segment.ops.extend(display.backend.take_log());
+            }
+        }
+    }
+
+    fn end_recording(&mut self) -> anyhow::Result<()> {
+        self.collect_log();
+        if let Some(recording) = self.recording.take().filter(|r| !r.is_empty()) {
+            self.logs.push(recording.save()?);
+        }
+        Ok(())
+    }
+
+    /// Enters the chapter of the replay's current segment, returning `false`
+    /// once there are none left.
+    fn start_segment(&mut self) -> anyhow::Result<bool> {
+        let Some(replay) = &self.replay else {
+            return Ok(false);
+        };
+        let Some(segment) = replay.segments.get(replay.segment) else {
+            return Ok(false);
+        };
+        if segment.chapter >= self.book.chapter_count() {
+            return Ok(false);
+        }
+        self.chapter = segment.chapter;
+        self.saved.progress = Some(Progress {
+            chapter: segment.chapter,
+            cursor: segment.start,
+        });
+        self.enter_chapter()?;
+        if let (Some(replay), State::Chapter(display)) = (&mut self.replay, &mut self.state) {
+            replay.op = 0;
+            replay.started = Instant::now();
+            display.replay_clock = Some((replay.started, replay.speed));
+        }
+        Ok(true)
+    }
+
+    /// Makes the next edit of the replay once it's due. Edits are made one at
+    /// a time so that each is rendered.
+    fn step_replay(&mut self) -> anyhow::Result<()> {
+        let (Some(replay), State::Chapter(display)) = (&mut self.replay, &mut self.state) else {
+            return Ok(());
+        };
+        let ops = replay.segments.get(replay.segment).map(|s| &s.ops[..]);
+        if let Some(&(time, op)) = ops.and_then(|ops| ops.get(replay.op)) {
+            if replay.started.elapsed() >= time.div_f64(replay.speed) {
+                display.backend.apply(op, time);
+                replay.op += 1;
+            }
+            return Ok(());
+        }
+
+        let score = display.backend.score();
+        match &mut replay.score {
+            Some(total) => total.combine(score),
+            None => replay.score = Some(score),
+        }
+        replay.segment += 1;
+        if !self.start_segment()? {
+            let score = self.replay.as_mut().and_then(|r| r.score.take());
+            self.state = State::Score(ScoreDisplay {
+                dimensions: Arc::clone(&self.dimensions),
+                title: String::from("Replay"),
+                score: score.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+
+    /// How often `tick` needs calling, if at all.
+    pub fn tick_rate(&self) -> Option<Duration> {
+        if let (Some(replay), State::Chapter(_)) = (&self.replay, &self.state) {
+            let due = replay
+                .segments
+                .get(replay.segment)
+                .and_then(|s| s.ops.get(replay.op))
+                .map_or(Duration::ZERO, |&(time, _)| time.div_f64(replay.speed));
+            return Some(due.saturating_sub(replay.started.elapsed()));
+        }
+        let session = match (self.options.goal, &self.session) {
+            (Goal::Time(_), Some(_)) => Some(Duration::from_millis(250)),
+            _ => None,
+        };
+        // keeps the status line's clock and any ghost cursor moving
+        let chapter = match &self.state {
+            State::Chapter(display) if display.ghost.is_some() => Some(Duration::from_millis(50)),
+            State::Chapter(_) => Some(Duration::from_millis(500)),
+            _ => None,
+        };
+        session.into_iter().chain(chapter).min()
+    }
+
+    /// Advances a replay, and ends a timed session once time is up, even
+    /// without any input. Rendering afterwards updates the status line and
+    /// moves any ghost cursor along.
+    pub fn tick(&mut self) -> anyhow::Result<()> {
+        if self.replay.is_some() {
+            self.step_replay()?;
+        } else if self.session_complete() {
+            self.finish_session()?;
+        }
+        Ok(())
+    }
+
+    fn session_complete(&self) -> bool {
+        let (Some(session), State::Chapter(display)) = (&self.session, &self.state) else {
+            return false;
+        };
+        match self.options.goal {
+            Goal::Chapter => false,
+            Goal::Time(time) => session.elapsed + display.backend.elapsed() >= time,
+            Goal::Words(words) => {
+                session.words + display.backend.words_typed_since(session.start) >= words
+            }
+        }
+    }
+
+    /// Adds the current chapter to the session.
+    fn bank_chapter(&mut self) {
+        let (Some(session), State::Chapter(display)) = (&mut self.session, &self.state) else {
+            return;
+        };
+        let backend = &display.backend;
+        match &mut session.score {
+            Some(score) => score.combine(backend.score()),
+            None => session.score = Some(backend.score()),
+        }
+        session.elapsed += backend.elapsed();
+        session.words += backend.words_typed_since(session.start);
+        session.start = Len::default();
+    }
+
+    fn finish_session(&mut self) -> anyhow::Result<()> {
+        let State::Chapter(display) = &self.state else {
+            return Ok(());
+        };
+        match display.backend.is_finished() {
+            true => self.advance_chapter()?,
+            false => self.save_progress()?,
+        }
+        self.bank_chapter();
+        self.end_recording()?;
+        let Some(session) = self.session.take() else {
+            return Ok(());
+        };
+
+        let title = match self.options.goal {
+            Goal::Time(time) => format!("{} session", format_duration(time)),
+            Goal::Words(words) => format!("{words} word session"),
+            Goal::Chapter => String::new(),
+        };
+        let score = session.score.unwrap_or_default();
+        self.save_history(session.chapter, &score)?;
+        self.state = State::Score(ScoreDisplay {
+            dimensions: Arc::clone(&self.dimensions),
+            title,
+            score,
+        });
         Ok(())
     }
 
+    fn save_progress(&mut self) -> anyhow::Result<()> {
+        let State::Chapter(display) = &self.state else {
+            return Ok(());
+        };
+        self.saved.progress = Some(Progress {
+            chapter: self.chapter,
+            cursor: display.backend.cursor(),
+        });
+        self.save_stats()?;
+        self.save_state()
+    }
+
+    /// Saves the book's state, unless replaying someone else's session or
+    /// drilling.
+    fn save_state(&self) -> anyhow::Result<()> {
+        match (&self.replay, &self.drill) {
+            (None, None) => self.saved.save(self.book.identifier()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds a completed session to the history.
+    fn save_history(&self, chapter: usize, score: &Score) -> anyhow::Result<()> {
+        if self.replay.is_some() || self.drill.is_some() || score.keystrokes == 0 {
+            return Ok(());
+        }
+        Entry::new(self.book.identifier(), self.book.title(), chapter, score).save()
+    }
+
+    /// Adds the keystrokes of the chapter being left to the statistics.
+    fn save_stats(&self) -> anyhow::Result<()> {
+        let State::Chapter(display) = &self.state else {
+            return Ok(());
+        };
+        let stats = Stats::from_keystrokes(display.backend.keystrokes());
+        match self.replay.is_none() && !stats.is_empty() {
+            true => Stats::save_merged(&stats),
+            false => Ok(()),
+        }
+    }
+
+    fn finish_chapter(&mut self) -> anyhow::Result<()> {
+        let State::Chapter(display) = &self.state else {
+            return Ok(());
+        };
+        if self.drill.is_some() {
+            let score = display.backend.score();
+            self.save_stats()?;
+            self.state = State::Score(ScoreDisplay {
+                dimensions: Arc::clone(&self.dimensions),
+                title: String::from("Drill"),
+                score,
+            });
+            return Ok(());
+        }
+        if self.session.is_some() {
+            if self.chapter + 1 >= self.book.chapter_count() {
+                return self.finish_session();
+            }
+            self.bank_chapter();
+            self.collect_log();
+            self.advance_chapter()?;
+            return self.try_enter_chapter();
+        }
+        let score = ScoreDisplay {
+            dimensions: Arc::clone(&self.dimensions),
+            title: self.book.chapters()[self.chapter].name().to_owned(),
+            score: display.backend.score(),
+        };
+
+        self.save_history(self.chapter, &score.score)?;
+        self.advance_chapter()?;
+        self.end_recording()?;
+        self.state = State::Score(score);
+        Ok(())
+    }
+
+    fn advance_chapter(&mut self) -> anyhow::Result<()> {
+        self.save_stats()?;
+        self.save_run()?;
+        // the next session starts at the beginning of the following chapter
+        self.chapter = (self.chapter + 1).min(self.book.chapter_count().saturating_sub(1));
+        self.saved.progress = Some(Progress {
+            chapter: self.chapter,
+            cursor: Len::default(),
+        });
+        self.save_state()
+    }
+
+    /// Keeps the timing of the finished chapter if it's the fastest complete
+    /// run through it so far.
+    fn save_run(&self) -> anyhow::Result<()> {
+        let State::Chapter(display) = &self.state else {
+            return Ok(());
+        };
+        if self.replay.is_some() || !display.backend.is_finished() {
+            return Ok(());
+        }
+        let backend = &display.backend;
+        let len = backend.text().chars().count();
+        let Some(run) = Run::from_keystrokes(backend.keystrokes(), backend.skipped(), len) else {
+            return Ok(());
+        };
+        let identifier = self.book.identifier();
+        match Run::load(identifier, self.chapter)? {
+            Some(best) if best.chars() == run.chars() && best.elapsed() <= run.elapsed() => Ok(()),
+            _ => run.save(identifier, self.chapter),
+        }
+    }
+
     fn cleanup(w: &mut impl Write) -> anyhow::Result<()> {
         terminal::disable_raw_mode()?;
         queue!(w, ResetColor, cursor::Show, terminal::LeaveAlternateScreen)?;
@@ -116,7 +675,7 @@ impl Display {
 
     pub fn render(&mut self, w: &mut impl Write) -> anyhow::Result<()> {
         match &mut self.state {
-            State::ChapterSelect { .. } => self.full_render(w)?,
+            State::ChapterSelect { .. } | State::Score(..) => self.full_render(w)?,
             State::Chapter(display) => {
                 if display.render_chapter(w)? {
                     self.full_render(w)?;
@@ -131,14 +690,17 @@ impl Display {
             State::ChapterSelect => {
                 queue!(w, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
 
-                let chapter = self.book.chapters().nth(self.chapter).unwrap();
+                let chapter = &self.book.chapters()[self.chapter];
                 let depth_offset = 2 * chapter.depth();
                 let wrap_at = self.content_width() as usize - depth_offset;
                 let wrapped = textwrap::wrap(chapter.name(), wrap_at);
                 let line = self.middle_row() - (wrapped.len() as u16 - 1) / 2;
                 queue!(
                     w,
-                    cursor::MoveTo(self.content_starting_col() - 2, self.middle_row())
+                    cursor::MoveTo(
+                        self.content_starting_col().saturating_sub(2),
+                        self.middle_row()
+                    )
                 )?;
                 w.write_all(b">")?;
                 for (i, wrap) in wrapped.iter().enumerate() {
@@ -158,6 +720,7 @@ impl Display {
                 'outer: for chapter in self
                     .book
                     .chapters()
+                    .iter()
                     .rev()
                     .skip(self.book.chapter_count() - self.chapter)
                 {
@@ -181,7 +744,7 @@ impl Display {
 
                     above -= u16::try_from(wrapped.len()).unwrap() + 1;
                 }
-                'outer: for chapter in self.book.chapters().skip(self.chapter + 1) {
+                'outer: for chapter in self.book.chapters().iter().skip(self.chapter + 1) {
                     let depth_offset = 2 * chapter.depth();
                     let wrap_at = self.content_width() as usize - depth_offset;
                     let wrapped = textwrap::wrap(chapter.name(), wrap_at);
@@ -202,26 +765,63 @@ impl Display {
 
                     below += u16::try_from(wrapped.len()).unwrap() + 1;
                 }
+                if let Some(message) = &self.message {
+                    let message: String = message
+                        .chars()
+                        .take(self.screen_width().saturating_sub(1) as usize)
+                        .collect();
+                    queue!(
+                        w,
+                        cursor::MoveTo(0, self.screen_height() - 1),
+                        SetForegroundColor(Color::Red)
+                    )?;
+                    w.write_all(message.as_bytes())?;
+                    queue!(w, ResetColor)?;
+                }
                 w.flush()?;
                 Ok(())
             }
             State::Chapter(display) => display.full_render_chapter(w),
+            State::Score(display) => display.render(w),
         }
     }
 
     pub fn handle_input(&mut self, event: KeyEvent) -> anyhow::Result<bool> {
+        let quits = matches!(
+            (&self.state, event.code),
+            (_, KeyCode::Esc) | (State::Score(..), KeyCode::Enter)
+        );
+        if self.replay.is_some() || (self.drill.is_some() && quits) {
+            return Ok(quits);
+        }
         if let KeyEvent {
             code: KeyCode::Esc, ..
         } = &event
         {
             match &mut self.state {
                 State::ChapterSelect => return Ok(true),
-                State::Chapter(..) => {
+                State::Chapter(display) => {
+                    // a session that got anywhere ends with its summary
+                    let typed = self.session.as_ref().is_some_and(|session| {
+                        session.score.is_some() || !display.backend.keystrokes().is_empty()
+                    });
+                    if typed {
+                        self.finish_session()?;
+                        return Ok(false);
+                    }
+                    self.save_progress()?;
+                    self.end_recording()?;
+                    self.session = None;
+                    self.state = State::ChapterSelect;
+                    return Ok(false);
+                }
+                State::Score(..) => {
                     self.state = State::ChapterSelect;
                     return Ok(false);
                 }
             }
         }
+        self.message = None;
         match &mut self.state {
             State::ChapterSelect => match event {
                 KeyEvent {
@@ -238,16 +838,23 @@ impl Display {
                 KeyEvent {
                     code: KeyCode::Enter,
                     ..
-                } => {
-                    self.state = State::Chapter(ChapterDisplay::enter(
-                        Arc::clone(&self.dimensions),
-                        &mut self.book,
-                        self.chapter,
-                    ));
-                }
+                } => self.try_enter_chapter()?,
                 _ => {}
             },
-            State::Chapter(display) => display.handle_input(event)?,
+            State::Chapter(display) => {
+                display.handle_input(event)?;
+                let finished = display.backend.is_finished();
+                if self.session_complete() {
+                    self.finish_session()?;
+                } else if finished {
+                    self.finish_chapter()?;
+                }
+            }
+            State::Score(..) => {
+                if let KeyCode::Enter = event.code {
+                    self.state = State::ChapterSelect;
+                }
+            }
         }
         Ok(false)
     }
@@ -257,8 +864,23 @@ struct ChapterDisplay {
     dimensions: Arc<Dimensions>,
     backend: Backend,
     lines: Vec<VirtualLine>,
+    images: Vec<ImagePlacement>,
+    graphics: Protocol,
     previous_line: usize,
     needs_full_render: bool,
+    /// Where typing started.
+    start: Len,
+    ghost: Option<Ghost>,
+    /// Character the ghost cursor was last drawn on.
+    ghost_drawn: Option<usize>,
+    /// Book title and chapter name, for the status line.
+    heading: String,
+    /// When the replay of the chapter started and how fast it plays, so the
+    /// clock keeps to the recorded times.
+    replay_clock: Option<(Instant, f64)>,
+    /// Fractions of the book before the chapter and once it's done, if the
+    /// text is from a book.
+    book_share: Option<Range<f64>>,
 }
 
 trait DisplayState {
@@ -297,40 +919,194 @@ impl DisplayState for ChapterDisplay {
     }
 }
 
+impl DisplayState for ScoreDisplay {
+    fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+}
+
 impl ChapterDisplay {
-    pub fn enter(dimensions: Arc<Dimensions>, book: &mut Epub, chapter: usize) -> Self {
-        let backend = Backend::new(book, chapter);
-        let lines = Self::wrap_text(backend.text(), dimensions.width);
+    pub fn enter(
+        dimensions: Arc<Dimensions>,
+        book: &mut dyn Book,
+        chapter: usize,
+        options: &Options,
+        quotes: Quotes,
+        config: Arc<Config>,
+    ) -> anyhow::Result<Self> {
+        let backend = Backend::new(
+            book,
+            chapter,
+            quotes,
+            options.notes,
+            config,
+            options.transliterate,
+            options.errors,
+        )?;
+        Ok(Self::new(dimensions, backend, options))
+    }
 
-        Self {
+    fn new(dimensions: Arc<Dimensions>, backend: Backend, options: &Options) -> Self {
+        let mut display = Self {
             dimensions,
             backend,
-            lines,
+            lines: Vec::new(),
+            images: Vec::new(),
+            graphics: options.graphics,
             previous_line: 0,
             needs_full_render: true,
-        }
+            start: Len::default(),
+            ghost: None,
+            ghost_drawn: None,
+            replay_clock: None,
+            heading: String::new(),
+            book_share: None,
+        };
+        display.layout();
+        display
+    }
+
+    /// Rows the text is rendered in, the last row of the screen is left for
+    /// the status line.
+    fn text_height(&self) -> u16 {
+        self.screen_height().saturating_sub(1).max(1)
+    }
+
+    fn resize(&mut self, dimensions: Arc<Dimensions>) {
+        self.dimensions = dimensions;
+        self.layout();
+        // the cursor stays on the same character, `full_render_chapter` works
+        // out which line that now is
+        self.needs_full_render = true;
+    }
+
+    fn layout(&mut self) {
+        let max_rows = (self.screen_height() / 2).max(1);
+        let pictures: Vec<_> = self
+            .backend
+            .images()
+            .iter()
+            .map(|image| match (self.graphics, &image.data) {
+                (Protocol::Text, _) | (_, None) => None,
+                (_, Some(data)) => Picture::new(data, self.content_width(), max_rows).ok(),
+            })
+            .collect();
+        let gaps: Vec<_> = self
+            .backend
+            .images()
+            .iter()
+            .zip(&pictures)
+            .map(|(image, picture)| (image.position, picture.as_ref().map_or(1, Picture::rows)))
+            .collect();
+
+        let (lines, image_lines) = Self::wrap_text(&self.backend, self.content_width(), &gaps);
+        self.lines = lines;
+        self.images = pictures
+            .into_iter()
+            .zip(image_lines)
+            .zip(gaps)
+            .enumerate()
+            .map(|(image, ((picture, line), (_, rows)))| ImagePlacement {
+                image,
+                line,
+                rows,
+                picture,
+                transmitted: Cell::new(false),
+            })
+            .collect();
     }
 
-    fn wrap_text(text: &str, width: u16) -> Vec<VirtualLine> {
+    /// Wraps `text` into lines, leaving `rows` empty lines after the paragraph
+    /// ending at each gap's position for images. Returns the lines and the
+    /// first line of each gap. Lines take the kind and alignment of their
+    /// paragraph, and headers are followed by an extra empty line.
+    fn wrap_text(
+        backend: &Backend,
+        width: u16,
+        gaps: &[(Len, u16)],
+    ) -> (Vec<VirtualLine>, Vec<usize>) {
+        let text = backend.text();
+        let paragraphs = backend.paragraphs();
+        // the paragraph containing the byte at `start`, its kind and the
+        // width it's wrapped to
+        let block_at = |start: usize| {
+            let idx = paragraphs.partition_point(|p| p.end.bytes < start);
+            let kind = backend.kinds().get(idx).copied().unwrap_or_default();
+            let indent = match kind {
+                BlockKind::Quote => QUOTE_INDENT,
+                _ => 0,
+            };
+            (idx, kind, indent, width.saturating_sub(indent).max(1))
+        };
+        let offset_at = |idx: usize, indent: u16, width: u16, chars: usize| {
+            let align = backend
+                .alignments()
+                .get(idx)
+                .copied()
+                .flatten()
+                .unwrap_or(Align::Left);
+            let space = width.saturating_sub(chars.try_into().unwrap_or(u16::MAX));
+            indent
+                + match align {
+                    Align::Left => 0,
+                    Align::Center => space / 2,
+                    Align::Right => space,
+                }
+        };
+
         let mut lines = vec![];
         let mut byte_sum = 0;
         let mut char_sum = 0;
         let mut line_number = 0;
 
-        let wrapped = textwrap::wrap(text, width as usize);
+        let mut gap_lines = Vec::with_capacity(gaps.len());
+        let mut gaps = gaps.iter().peekable();
+        let mut place_gaps_at = |line_number: &mut usize, position: usize| {
+            while let Some((_, rows)) = gaps.next_if(|(p, _)| p.bytes == position) {
+                gap_lines.push(*line_number);
+                *line_number += *rows as usize + 1;
+            }
+        };
+        place_gaps_at(&mut line_number, 0);
+
+        // each line of the text is wrapped to the width of its paragraph, the
+        // wrapped lines still borrowing from `text`. Preformatted text and
+        // tables keep their lines as they are, to stay lined up
+        let mut wrapped = Vec::new();
+        let mut start = 0;
+        for line in text.split('\n') {
+            match block_at(start) {
+                (_, BlockKind::Preformatted | BlockKind::TableRow, ..) => {
+                    wrapped.push(Cow::Borrowed(line))
+                }
+                (.., width) => wrapped.extend(textwrap::wrap(line, width as usize)),
+            }
+            start += line.len() + 1;
+        }
         let mut it = wrapped.into_iter();
         let mut prev = it.next();
         while let (Some(line), Some(next)) = (prev, it.next()) {
             let this_line = line_number;
             let line_chars = line.chars().count();
             let end = byte_sum + line.len();
+            let (idx, kind, indent, width) = block_at(byte_sum);
             let (separator_len, linebreak) = {
                 let next_start = next.as_ptr() as usize - text.as_ptr() as usize;
                 let len = next_start - end;
                 let separator = &text[end..next_start];
-                let kind = match separator.contains('\n') {
-                    true => {
+                let linebreak = match separator.contains('\n') {
+                    // paragraphs are followed by a blank line, but lines
+                    // within one, as in preformatted text, aren't
+                    true if paragraphs[idx].end.bytes == end => {
                         line_number += 2;
+                        if kind == BlockKind::Header {
+                            line_number += 1;
+                        }
+                        place_gaps_at(&mut line_number, end);
+                        Linebreak::Existing
+                    }
+                    true => {
+                        line_number += 1;
                         Linebreak::Existing
                     }
                     false => {
@@ -338,37 +1114,55 @@ impl ChapterDisplay {
                         Linebreak::Wrapped
                     }
                 };
-                (Len::new(len, separator.chars().count()), kind)
+                (Len::new(len, separator.chars().count()), linebreak)
             };
+            let offset = offset_at(idx, indent, width, line_chars);
             lines.push(VirtualLine {
                 line: this_line,
                 start: Len::new(byte_sum, char_sum),
                 end: Len::new(end, char_sum + line_chars),
                 separator_len,
                 linebreak,
+                kind,
+                offset,
             });
             byte_sum += line.len() + separator_len.bytes;
             char_sum += line_chars + separator_len.chars;
             prev = Some(next);
         }
+        let line_chars = text[byte_sum..].chars().count();
+        let (idx, kind, indent, width) = block_at(byte_sum);
+        let offset = offset_at(idx, indent, width, line_chars);
         lines.push(VirtualLine {
             line: line_number,
             start: Len::new(byte_sum, char_sum),
-            end: Len::new(text.len(), char_sum + text[byte_sum..].chars().count()),
+            end: Len::new(text.len(), char_sum + line_chars),
             separator_len: Len::new(0, 0),
             linebreak: Linebreak::Eof,
+            kind,
+            offset,
         });
-        lines
+
+        // anything left trails the text
+        line_number += 2;
+        for (_, rows) in gaps {
+            gap_lines.push(line_number);
+            line_number += *rows as usize + 1;
+        }
+        (lines, gap_lines)
     }
 
     fn char_index_to_virtual_line(&self, idx: usize) -> usize {
         self.lines.partition_point(|e| e.end.chars < idx)
     }
 
+    /// Column of `cursor` within the content, counting its line's offset,
+    /// and its line.
     fn to_virtual(&self, cursor: usize) -> (u16, usize) {
         let y = self.char_index_to_virtual_line(cursor);
-        let x = cursor - self.lines[y].start.chars;
-        (x.try_into().unwrap(), y)
+        let line = &self.lines[y];
+        let x: u16 = (cursor - line.start.chars).try_into().unwrap();
+        (line.offset + x, y)
     }
 
     fn virtual_line_str(&self, vl: &VirtualLine) -> &str {
@@ -381,17 +1175,15 @@ impl ChapterDisplay {
             Bound::Excluded(&l) => l + 1,
             Bound::Unbounded => 0,
         }
-        .min(self.screen_height() - 1);
+        .min(self.text_height() - 1);
         let end_bound = match range.end_bound() {
             Bound::Included(&l) => l + 1,
             Bound::Excluded(&l) => l,
-            Bound::Unbounded => self.screen_height(),
+            Bound::Unbounded => self.text_height(),
         }
-        .min(self.screen_height());
+        .min(self.text_height());
 
-        let line = self.char_index_to_virtual_line(self.backend.cursor().chars);
-        let cursor_vln = self.lines[line].line;
-        let top_of_screen_vln = cursor_vln as isize - self.middle_row() as isize;
+        let top_of_screen_vln = self.top_of_screen();
         let start_vln = (top_of_screen_vln + start_bound as isize).max(0) as usize;
         let end_vln = (top_of_screen_vln + end_bound as isize).max(0) as usize;
         let offset = (start_vln as isize - top_of_screen_vln).max(0) as usize;
@@ -406,6 +1198,81 @@ impl ChapterDisplay {
             })
     }
 
+    /// Virtual line at the top of the screen, negative if the cursor is near
+    /// the start of the text.
+    fn top_of_screen(&self) -> isize {
+        let line = self.char_index_to_virtual_line(self.backend.cursor().chars);
+        self.lines[line].line as isize - self.middle_row() as isize
+    }
+
+    /// Images with any of their rows within `rows`, along with the row their
+    /// top is on.
+    fn screen_images(
+        &self,
+        rows: Range<u16>,
+    ) -> impl Iterator<Item = (&ImagePlacement, isize)> + '_ {
+        let top = self.top_of_screen();
+        self.images
+            .iter()
+            .map(move |image| (image, image.line as isize - top))
+            .filter(move |&(image, row)| {
+                row < rows.end as isize && row + image.rows as isize > rows.start as isize
+            })
+    }
+
+    fn render_image(
+        &self,
+        w: &mut impl Write,
+        image: &ImagePlacement,
+        row: isize,
+    ) -> anyhow::Result<()> {
+        let fits = row >= 0 && row + image.rows as isize <= self.text_height() as isize;
+        match &image.picture {
+            Some(picture) if fits => {
+                let col = self.content_starting_col() + (self.content_width() - picture.cols()) / 2;
+                queue!(w, cursor::MoveTo(col, row as u16))?;
+                let id = image.image as u32 + 1;
+                match self.graphics {
+                    Protocol::Kitty => {
+                        if !image.transmitted.replace(true) {
+                            w.write_all(picture.kitty_transmit(id).as_bytes())?;
+                        }
+                        w.write_all(picture.kitty_place(id).as_bytes())?;
+                    }
+                    Protocol::Sixel => w.write_all(picture.sixel().as_bytes())?,
+                    Protocol::Text => {}
+                }
+            }
+            _ => {
+                let placeholder = match self.backend.images()[image.image]
+                    .alt
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|alt| !alt.is_empty())
+                {
+                    Some(alt) => format!("[image: {alt}]"),
+                    None => String::from("[image]"),
+                };
+                let placeholder: String = placeholder
+                    .chars()
+                    .take(self.content_width() as usize)
+                    .collect();
+                let len = placeholder.chars().count() as u16;
+                queue!(
+                    w,
+                    cursor::MoveTo(
+                        self.content_starting_col() + (self.content_width() - len) / 2,
+                        row.max(0) as u16
+                    ),
+                    SetForegroundColor(Color::DarkGrey),
+                )?;
+                w.write_all(placeholder.as_bytes())?;
+                queue!(w, SetForegroundColor(Color::Reset))?;
+            }
+        }
+        Ok(())
+    }
+
     fn with_error<W>(
         &self,
         w: &mut W,
@@ -429,7 +1296,117 @@ impl ChapterDisplay {
     }
 
     fn render_line(&self, w: &mut impl Write, line: &ScreenLine) -> anyhow::Result<()> {
-        self.render_range_in_line(w, line, Len::new(0, 0), line.len_with_break())
+        if line.line.kind == BlockKind::Quote {
+            queue!(
+                w,
+                cursor::MoveTo(self.content_starting_col(), line.row),
+                SetForegroundColor(Color::DarkGrey)
+            )?;
+            write!(w, "{QUOTE_GUTTER}")?;
+            queue!(w, SetForegroundColor(Color::Reset))?;
+        }
+        self.render_range_in_line(w, line, Len::new(0, 0), line.len_with_break())?;
+        self.render_annotation(w, line)
+    }
+
+    /// Column of the gutter annotations are rendered in: to the right of the
+    /// content if it fits, otherwise to the left.
+    fn annotation_col(&self) -> Option<u16> {
+        let right = self.content_starting_col() + self.content_width() + 2;
+        if right + ANNOTATION_WIDTH <= self.screen_width() {
+            Some(right)
+        } else {
+            self.content_starting_col()
+                .checked_sub(ANNOTATION_WIDTH + 2)
+        }
+    }
+
+    fn render_annotation(&self, w: &mut impl Write, line: &ScreenLine) -> anyhow::Result<()> {
+        let Some(col) = self.annotation_col() else {
+            return Ok(());
+        };
+        queue!(w, cursor::MoveTo(col, line.row))?;
+        write!(w, "{:1$}", "", ANNOTATION_WIDTH as usize)?;
+
+        let paragraphs = self.backend.paragraphs();
+        let idx = paragraphs.partition_point(|p| p.end < line.line.end);
+        match paragraphs.get(idx) {
+            Some(p) if p.end == line.line.end => {}
+            _ => return Ok(()),
+        }
+        if let Some(score) = self.backend.paragraph_score(idx) {
+            queue!(
+                w,
+                cursor::MoveTo(col, line.row),
+                SetForegroundColor(Color::DarkGrey)
+            )?;
+            write!(
+                w,
+                "{:>3.0}wpm {:>3.0}%",
+                score.gross_wpm(),
+                score.accuracy() * 100.0
+            )?;
+            queue!(w, SetForegroundColor(Color::Reset))?;
+        }
+        Ok(())
+    }
+
+    /// Column and width of the panel notes are shown in, to the right of the
+    /// content and its annotations, if there's room for one.
+    fn notes_panel(&self) -> Option<(u16, u16)> {
+        let content_end = self.content_starting_col() + self.content_width() + 2;
+        let col = match self.annotation_col() {
+            Some(col) if col >= content_end => col + ANNOTATION_WIDTH + 2,
+            _ => content_end,
+        };
+        let width = self.screen_width().checked_sub(col)?;
+        (width >= MIN_NOTES_WIDTH).then_some((col, width))
+    }
+
+    /// Notes of the paragraph the cursor is in.
+    fn current_notes(&self) -> impl Iterator<Item = &str> {
+        let paragraphs = self.backend.paragraphs();
+        let idx = paragraphs.partition_point(|p| p.end < self.backend.cursor());
+        let end = paragraphs.get(idx).map(|p| p.end);
+        self.backend
+            .notes()
+            .iter()
+            .filter(move |note| Some(note.position) == end)
+            .map(|note| note.text.as_str())
+    }
+
+    /// Shows the notes of the paragraph the cursor is in, from the cursor's
+    /// row down.
+    fn render_notes(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        if self.backend.notes().is_empty() {
+            return Ok(());
+        }
+        let Some((col, width)) = self.notes_panel() else {
+            return Ok(());
+        };
+        for row in 0..self.text_height() {
+            queue!(
+                w,
+                cursor::MoveTo(col, row),
+                terminal::Clear(terminal::ClearType::UntilNewLine)
+            )?;
+        }
+
+        let lines: Vec<_> = self
+            .current_notes()
+            .flat_map(|note| {
+                let mut lines = textwrap::wrap(note, width as usize);
+                lines.push("".into());
+                lines
+            })
+            .collect();
+        queue!(w, SetForegroundColor(Color::DarkGrey))?;
+        for (row, line) in (self.middle_row()..self.text_height()).zip(&lines) {
+            queue!(w, cursor::MoveTo(col, row))?;
+            write!(w, "{line}")?;
+        }
+        queue!(w, SetForegroundColor(Color::Reset))?;
+        Ok(())
     }
 
     fn render_range_in_line(
@@ -441,28 +1418,34 @@ impl ChapterDisplay {
     ) -> anyhow::Result<()> {
         queue!(
             w,
-            cursor::MoveTo(self.content_starting_col() + start.chars as u16, line.row)
+            cursor::MoveTo(
+                self.content_starting_col() + line.line.offset + start.chars as u16,
+                line.row
+            )
         )?;
+        // lines that aren't wrapped are cut off at the edge of the content
+        let columns = self.content_width().saturating_sub(line.line.offset) as usize;
+        if start.chars >= columns {
+            return Ok(());
+        }
+        let line_str = self.virtual_line_str(line.line);
+        let end = match line_str.char_indices().nth(columns) {
+            Some((bytes, _)) => end.min(Len::new(bytes, columns)),
+            None => end,
+        };
         let slice_end = end.min(line.len());
-        let mut text = self.virtual_line_str(line.line)[start.bytes..slice_end.bytes].as_bytes();
+        let mut text = line_str[start.bytes..slice_end.bytes].as_bytes();
         let mut cur_style = Style::empty();
+        let block_style = match line.line.kind {
+            BlockKind::Header => Style::BOLD,
+            _ => Style::empty(),
+        };
         for (style, len) in self
             .backend
             .style_iter(line.line.start + start, line.line.start + slice_end)
         {
-            for attr in (cur_style & !style)
-                .iter()
-                .filter_map(|s| match s {
-                    Style::BOLD => Some(Attribute::NormalIntensity),
-                    Style::ITALIC => Some(Attribute::NoItalic),
-                    _ => None,
-                })
-                .chain((style & !cur_style).iter().filter_map(|s| match s {
-                    Style::BOLD => Some(Attribute::Bold),
-                    Style::ITALIC => Some(Attribute::Italic),
-                    _ => None,
-                }))
-            {
+            let style = style | block_style;
+            for attr in style_attributes(cur_style, style) {
                 crossterm::queue!(w, SetAttribute(attr))?;
             }
             w.write_all(&text[..len.bytes])?;
@@ -481,6 +1464,155 @@ impl ChapterDisplay {
         Ok(())
     }
 
+    fn render_status(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        let row = self.screen_height() - 1;
+        queue!(
+            w,
+            cursor::MoveTo(0, row),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )?;
+        let mut parts = Vec::new();
+        if let Some(mode) = self.backend.error_mode().description() {
+            parts.push(mode.to_owned());
+        }
+        if let Some(ghost) = self.ghost_position() {
+            let ahead = self.backend.cursor().chars as isize - ghost as isize;
+            parts.push(match ahead.cmp(&0) {
+                Ordering::Greater => format!("{ahead} ahead of ghost"),
+                Ordering::Less => format!("{} behind ghost", -ahead),
+                Ordering::Equal => String::from("level with ghost"),
+            });
+        }
+        let chapter = match self.chapter_len() {
+            0 => 1.0,
+            len => self.backend.cursor().chars as f64 / len as f64,
+        };
+        parts.push(format!("{:.0}%", chapter * 100.0));
+        if let Some(share) = &self.book_share {
+            let book = share.start + (share.end - share.start) * chapter;
+            parts.push(format!("{:.0}% of book", book * 100.0));
+        }
+        let score = self.backend.totals();
+        parts.push(format!(
+            "{:.0}wpm · {:.0}% · {}",
+            score.gross_wpm(),
+            score.accuracy() * 100.0,
+            format_duration(self.elapsed()),
+        ));
+
+        // without room for a panel, notes take the heading's place
+        let notes: Vec<_> = match self.notes_panel() {
+            Some(_) => Vec::new(),
+            None => self.current_notes().collect(),
+        };
+        let heading = match notes.is_empty() {
+            true => self.heading.clone(),
+            false => notes.join(" · "),
+        };
+
+        // the stats are right-aligned, and the heading gets what's left
+        let width = self.content_width() as usize;
+        let stats: String = parts.join(" · ").chars().take(width).collect();
+        let stats_len = stats.chars().count();
+        let heading_width = width.saturating_sub(stats_len + 2);
+        let heading: String = match heading.chars().count() > heading_width {
+            true if heading_width > 1 => heading
+                .chars()
+                .take(heading_width - 1)
+                .chain(['…'])
+                .collect(),
+            true => String::new(),
+            false => heading,
+        };
+        queue!(
+            w,
+            cursor::MoveTo(self.content_starting_col(), row),
+            SetForegroundColor(Color::DarkGrey)
+        )?;
+        w.write_all(heading.as_bytes())?;
+        queue!(
+            w,
+            cursor::MoveTo(
+                self.content_starting_col() + (width - stats_len) as u16,
+                row
+            )
+        )?;
+        w.write_all(stats.as_bytes())?;
+        queue!(w, SetForegroundColor(Color::Reset))?;
+        Ok(())
+    }
+
+    /// Length of the chapter's text in characters.
+    fn chapter_len(&self) -> usize {
+        self.lines.last().map_or(0, |l| l.end.chars)
+    }
+
+    /// Character the ghost cursor is on, once typing has started.
+    fn ghost_position(&self) -> Option<usize> {
+        let ghost = self.ghost.as_ref()?;
+        if self.backend.keystrokes().is_empty() {
+            return None;
+        }
+        let position = ghost.position(self.start.chars, self.elapsed())?;
+        Some(position.min(self.chapter_len()))
+    }
+
+    /// Time since the first keystroke, as it was when recorded if replaying.
+    fn elapsed(&self) -> Duration {
+        match self.replay_clock {
+            Some((started, speed)) => self.backend.elapsed_at(started.elapsed().mul_f64(speed)),
+            None => self.backend.elapsed(),
+        }
+    }
+
+    /// Renders the character at `idx`, if it's on screen, with the ghost
+    /// cursor on it or without.
+    fn render_char(&self, w: &mut impl Write, idx: usize, ghost: bool) -> anyhow::Result<()> {
+        let vl = &self.lines[self.char_index_to_virtual_line(idx)];
+        let row = vl.line as isize - self.top_of_screen();
+        if row < 0 || row >= self.text_height() as isize {
+            return Ok(());
+        }
+        let line = ScreenLine {
+            line: vl,
+            row: row as u16,
+        };
+        let offset = idx - vl.start.chars;
+        let rest = &self.backend.text()[vl.start.bytes..];
+        let Some((bytes, c)) = rest.char_indices().nth(offset) else {
+            return Ok(());
+        };
+        let x = Len::new(bytes, offset);
+        let len = Len::new(c.len_utf8(), 1);
+        let is_error = idx < self.backend.cursor().chars
+            && self.backend.errors().binary_search(&(vl.start + x)).is_ok();
+        if ghost {
+            queue!(w, SetBackgroundColor(Color::DarkGrey))?;
+        }
+        match is_error {
+            true => self.with_error(w, |w| self.render_range_in_line(w, &line, x, x + len))?,
+            false => self.render_range_in_line(w, &line, x, x + len)?,
+        }
+        queue!(w, SetBackgroundColor(Color::Reset))?;
+        Ok(())
+    }
+
+    /// Moves the ghost cursor to where it is now.
+    fn render_ghost(&mut self, w: &mut impl Write, force: bool) -> anyhow::Result<()> {
+        let position = self.ghost_position();
+        if position == self.ghost_drawn && !force {
+            return Ok(());
+        }
+        if let Some(previous) = self.ghost_drawn {
+            self.render_char(w, previous, false)?;
+        }
+        if let Some(position) = position {
+            self.render_char(w, position, true)?;
+        }
+        self.ghost_drawn = position;
+        Ok(())
+    }
+
     fn line_difference(&self, current_line: usize) -> isize {
         self.lines[current_line].line as isize - self.lines[self.previous_line].line as isize
     }
@@ -498,19 +1630,49 @@ impl ChapterDisplay {
 
         queue!(w, cursor::Hide)?;
 
+        if lines_scrolled >= self.text_height() {
+            return Ok(true);
+        }
+
         if lines_scrolled > 0 {
             let range = if y > self.previous_line {
-                queue!(w, terminal::ScrollUp(lines_scrolled))?;
-                let bottom = self.screen_height();
+                let bottom = self.text_height();
                 bottom - lines_scrolled..bottom
             } else {
-                queue!(w, terminal::ScrollDown(lines_scrolled))?;
                 0..lines_scrolled
             };
-            for line in self.screen_lines(range) {
+            // pictures can only be drawn whole, so redraw everything instead
+            // of scrolling one into view piece by piece
+            if self
+                .screen_images(range.clone())
+                .any(|(image, _)| image.picture.is_some())
+            {
+                return Ok(true);
+            }
+
+            match y > self.previous_line {
+                true => queue!(w, terminal::ScrollUp(lines_scrolled))?,
+                false => queue!(w, terminal::ScrollDown(lines_scrolled))?,
+            }
+            // the status line scrolls along with the text
+            for row in range.clone() {
+                queue!(
+                    w,
+                    cursor::MoveTo(0, row),
+                    terminal::Clear(terminal::ClearType::CurrentLine)
+                )?;
+            }
+            for line in self.screen_lines(range.clone()) {
                 self.render_line(w, &line)?;
             }
+            for (image, row) in self.screen_images(range) {
+                self.render_image(w, image, row)?;
+            }
+            // the paragraph may have changed, and the panel scrolled with the
+            // text
+            self.render_notes(w)?;
         }
+        self.render_status(w)?;
 
         // if self.cursor_prev.bytes >= self.line_starts[self.previous_line].end.bytes {
         //     let x = self.cursor_prev.chars - self.line_starts[self.previous_line].start.chars;
@@ -530,6 +1692,17 @@ impl ChapterDisplay {
         //     queue!(w, cursor::MoveLeft(1))?;
         // }
 
+        // paragraphs may have been finished or reopened
+        if lines_scrolled > 0 {
+            let range = match y.cmp(&self.previous_line) {
+                Ordering::Greater => self.middle_row() - lines_scrolled..=self.middle_row(),
+                _ => self.middle_row()..=self.middle_row() + lines_scrolled,
+            };
+            for line in self.screen_lines(range) {
+                self.render_annotation(w, &line)?;
+            }
+        }
+
         // TODO: too much complexity
         // error highlighting
         let cursor_pos = self.backend.cursor();
@@ -577,6 +1750,9 @@ impl ChapterDisplay {
             }
         }
 
+        // the ghost is drawn over any error highlighting
+        self.render_ghost(w, lines_scrolled > 0)?;
+
         queue!(
             w,
             cursor::MoveTo(self.content_starting_col() + x, self.middle_row()),
@@ -590,18 +1766,29 @@ impl ChapterDisplay {
     }
 
     fn full_render_chapter(&mut self, w: &mut impl Write) -> anyhow::Result<()> {
-        let (x, _y) = self.to_virtual(self.backend.cursor().chars);
+        let (x, y) = self.to_virtual(self.backend.cursor().chars);
 
         queue!(w, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+        if self.graphics == Protocol::Kitty {
+            w.write_all(KITTY_CLEAR.as_bytes())?;
+        }
         for line in self.screen_lines(..) {
             self.render_line(w, &line)?;
         }
+        for (image, row) in self.screen_images(0..self.text_height()) {
+            self.render_image(w, image, row)?;
+        }
+        self.render_status(w)?;
+        self.render_notes(w)?;
+        self.ghost_drawn = None;
+        self.render_ghost(w, true)?;
         queue!(
             w,
             cursor::MoveTo(self.content_starting_col() + x, self.middle_row()),
             cursor::Show,
         )?;
         w.flush()?;
+        self.previous_line = y;
         self.needs_full_render = false;
         Ok(())
     }
@@ -630,3 +1817,136 @@ impl ChapterDisplay {
         Ok(())
     }
 }
+
+struct ScoreDisplay {
+    dimensions: Arc<Dimensions>,
+    title: String,
+    score: Score,
+}
+
+impl ScoreDisplay {
+    fn render(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        fn visible(c: char) -> char {
+            match c {
+                '\n' => '↵',
+                ' ' => '␣',
+                c => c,
+            }
+        }
+
+        let score = &self.score;
+        let most_missed = score
+            .most_missed(5)
+            .into_iter()
+            .map(|(c, n)| format!("{} ×{n}", visible(c)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let worst_words = score
+            .worst_words(5)
+            .into_iter()
+            .map(|(word, n)| format!("{word} ×{n}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let lines = [
+            self.title.clone(),
+            String::new(),
+            format!("gross wpm     {:.1}", score.gross_wpm()),
+            format!("net wpm       {:.1}", score.net_wpm()),
+            format!("accuracy      {:.1}%", score.accuracy() * 100.0),
+            format!("time          {}", format_duration(score.elapsed)),
+            format!(
+                "errors        {} ({} corrected)",
+                score.errors,
+                score.errors.saturating_sub(score.uncorrected)
+            ),
+            String::new(),
+            format!("most missed   {most_missed}"),
+            format!("worst words   {worst_words}"),
+        ];
+
+        queue!(w, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+        let top = self.middle_row().saturating_sub(lines.len() as u16 / 2);
+        for (i, line) in lines.iter().enumerate() {
+            queue!(
+                w,
+                cursor::MoveTo(self.content_starting_col(), top + i as u16)
+            )?;
+            let end = line
+                .char_indices()
+                .nth(self.content_width() as usize)
+                .map_or(line.len(), |(i, _)| i);
+            w.write_all(&line.as_bytes()[..end])?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// Attributes that change text shown in `from` to `to`. Smaller and
+/// monospaced text is dim, since reversed text is how errors are shown.
+fn style_attributes(from: Style, to: Style) -> Vec<Attribute> {
+    let dim = Style::SMALL | Style::SUPERSCRIPT | Style::SUBSCRIPT | Style::MONOSPACE;
+    let mut attributes = Vec::new();
+    // bold and dim are both turned off by the same attribute
+    let (bold, was_bold) = (to.contains(Style::BOLD), from.contains(Style::BOLD));
+    let (dimmed, was_dimmed) = (to.intersects(dim), from.intersects(dim));
+    let reset = (was_bold && !bold) || (was_dimmed && !dimmed);
+    if reset {
+        attributes.push(Attribute::NormalIntensity);
+    }
+    if bold && (reset || !was_bold) {
+        attributes.push(Attribute::Bold);
+    }
+    if dimmed && (reset || !was_dimmed) {
+        attributes.push(Attribute::Dim);
+    }
+    for (style, on, off) in [
+        (Style::ITALIC, Attribute::Italic, Attribute::NoItalic),
+        (
+            Style::UNDERLINE,
+            Attribute::Underlined,
+            Attribute::NoUnderline,
+        ),
+        (
+            Style::STRIKETHROUGH,
+            Attribute::CrossedOut,
+            Attribute::NotCrossedOut,
+        ),
+    ] {
+        match (from.contains(style), to.contains(style)) {
+            (false, true) => attributes.push(on),
+            (true, false) => attributes.push(off),
+            _ => {}
+        }
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{backend::test::from_html, notes::Notes};
+
+    #[test]
+    fn it_wraps_paragraphs_but_not_preformatted_text() {
+        let xml = "<html><body><p>One two three</p>
+<pre>fn main() {\n    body();\n}</pre><p>Four</p></body></html>";
+        let backend = from_html(xml, Notes::Skip);
+        let (lines, _) = ChapterDisplay::wrap_text(&backend, 8, &[]);
+        let rows: Vec<_> = lines
+            .iter()
+            .map(|l| (l.line, &backend.text()[l.start.bytes..l.end.bytes]))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (0, "One two"),
+                (1, "three"),
+                (3, "fn main() {"),
+                (4, "    body();"),
+                (5, "}"),
+                (7, "Four"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+/// ASCII transliteration of a character, for typing text that contains
+/// characters missing from the keyboard. Letters lose their diacritics and
+/// ligatures and letters without a single-letter equivalent (`ß`, `œ`, `θ`)
+/// become several.
+pub fn ascii(c: char) -> Option<&'static str> {
+    let s = match c {
+        // latin-1 supplement
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => "A",
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+        'Æ' => "Ae",
+        'æ' => "ae",
+        'Ç' => "C",
+        'ç' => "c",
+        'È' | 'É' | 'Ê' | 'Ë' => "E",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'Ð' => "D",
+        'ð' => "d",
+        'Ñ' => "N",
+        'ñ' => "n",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => "O",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => "o",
+        'Ù' | 'Ú' | 'Û' | 'Ü' => "U",
+        'ù' | 'ú' | 'û' | 'ü' => "u",
+        'Ý' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Þ' => "Th",
+        'þ' => "th",
+        'ß' => "ss",
+
+        // latin extended-a and common extended-b
+        'Ā' | 'Ă' | 'Ą' => "A",
+        'ā' | 'ă' | 'ą' => "a",
+        'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ď' | 'Đ' => "D",
+        'ď' | 'đ' => "d",
+        'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ĥ' | 'Ħ' => "H",
+        'ĥ' | 'ħ' => "h",
+        'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ĳ' => "IJ",
+        'ĳ' => "ij",
+        'Ĵ' => "J",
+        'ĵ' => "j",
+        'Ķ' => "K",
+        'ķ' | 'ĸ' => "k",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'Ń' | 'Ņ' | 'Ň' => "N",
+        'ń' | 'ņ' | 'ň' => "n",
+        'ŉ' => "'n",
+        'Ŋ' => "Ng",
+        'ŋ' => "ng",
+        'Ō' | 'Ŏ' | 'Ő' => "O",
+        'ō' | 'ŏ' | 'ő' => "o",
+        'Œ' => "Oe",
+        'œ' => "oe",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' | 'Ș' => "S",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' | 'ſ' => "s",
+        'Ţ' | 'Ť' | 'Ŧ' | 'Ț' => "T",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ŵ' => "W",
+        'ŵ' => "w",
+        'Ŷ' | 'Ÿ' => "Y",
+        'ŷ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        'ƒ' => "f",
+
+        // greek, accented letters are handled below
+        'Α' => "A",
+        'α' => "a",
+        'Β' => "B",
+        'β' => "b",
+        'Γ' => "G",
+        'γ' => "g",
+        'Δ' => "D",
+        'δ' => "d",
+        'Ε' | 'Η' => "E",
+        'ε' | 'η' => "e",
+        'Ζ' => "Z",
+        'ζ' => "z",
+        'Θ' => "Th",
+        'θ' => "th",
+        'Ι' => "I",
+        'ι' => "i",
+        'Κ' => "K",
+        'κ' => "k",
+        'Λ' => "L",
+        'λ' => "l",
+        'Μ' => "M",
+        'μ' | 'µ' => "m",
+        'Ν' => "N",
+        'ν' => "n",
+        'Ξ' => "X",
+        'ξ' => "x",
+        'Ο' | 'Ω' => "O",
+        'ο' | 'ω' => "o",
+        'Π' => "P",
+        'π' => "p",
+        'Ρ' => "R",
+        'ρ' => "r",
+        'Σ' => "S",
+        'σ' | 'ς' => "s",
+        'Τ' => "T",
+        'τ' => "t",
+        'Υ' => "Y",
+        'υ' => "y",
+        'Φ' => "Ph",
+        'φ' => "ph",
+        'Χ' => "Ch",
+        'χ' => "ch",
+        'Ψ' => "Ps",
+        'ψ' => "ps",
+        'Ά' | 'Έ' | 'Ή' | 'Ί' | 'Ϊ' | 'Ό' | 'Ύ' | 'Ϋ' | 'Ώ' | 'ά' | 'έ' | 'ή' | 'ί' | 'ϊ' | 'ΐ'
+        | 'ό' | 'ύ' | 'ϋ' | 'ΰ' | 'ώ' => return ascii(greek_base(c)),
+
+        // punctuation and symbols
+        '\u{a0}' | '\u{2002}'..='\u{200a}' => " ",
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => "-",
+        '‘' | '’' | '‚' | '‛' | '′' | '‹' | '›' => "'",
+        '“' | '”' | '„' | '‟' | '″' | '«' | '»' => "\"",
+        '…' => "...",
+        '•' | '·' => "*",
+        '×' => "x",
+        '÷' => "/",
+        '©' => "(c)",
+        '®' => "(r)",
+        '™' => "tm",
+        '½' => "1/2",
+        '¼' => "1/4",
+        '¾' => "3/4",
+        '¹' => "1",
+        '²' => "2",
+        '³' => "3",
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn greek_base(c: char) -> char {
+    match c {
+        'Ά' => 'Α',
+        'Έ' => 'Ε',
+        'Ή' => 'Η',
+        'Ί' | 'Ϊ' => 'Ι',
+        'Ό' => 'Ο',
+        'Ύ' | 'Ϋ' => 'Υ',
+        'Ώ' => 'Ω',
+        'ά' => 'α',
+        'έ' => 'ε',
+        'ή' => 'η',
+        'ί' | 'ϊ' | 'ΐ' => 'ι',
+        'ό' => 'ο',
+        'ύ' | 'ϋ' | 'ΰ' => 'υ',
+        'ώ' => 'ω',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_transliterates() {
+        let word = |s: &str| -> String {
+            s.chars()
+                .map(|c| ascii(c).map_or_else(|| c.to_string(), ToOwned::to_owned))
+                .collect()
+        };
+        assert_eq!(word("Ærøskøbing"), "Aeroskobing");
+        assert_eq!(word("Straße"), "Strasse");
+        assert_eq!(word("Ψυχή"), "Psyche");
+        assert_eq!(ascii('a'), None);
+    }
+}